@@ -1,14 +1,20 @@
 mod arg;
 mod endpoint;
+mod ke;
+mod matching;
 mod query;
 mod response;
 mod sample;
 
-mod callbacks;
+pub(crate) mod callbacks;
+pub(crate) mod consolidation;
+pub(crate) mod interests;
+pub(crate) mod keyexprs;
 
 mod config;
 
 mod driver;
+pub(crate) mod hlc;
 mod resources;
 
 mod session;
@@ -16,13 +22,25 @@ mod session;
 pub type ZResult<T> = core::result::Result<T, crate::Error>;
 
 pub use callbacks::{
-    FixedCapacityGetCallbacks, FixedCapacityQueryableCallbacks, FixedCapacitySubCallbacks, storage,
+    FixedCapacityCompletionCallbacks, FixedCapacityGetCallbacks, FixedCapacityMatchingCallbacks,
+    FixedCapacityQueryableCallbacks, FixedCapacitySubCallbacks, TrieCallbacks,
+    TrieQueryableCallbacks, TrieSubCallbacks, storage,
 };
 pub use config::*;
+pub use consolidation::FixedCapacityConsolidation;
 pub use endpoint::*;
+pub use interests::{EntityKind, FixedCapacityInterests};
+pub use ke::*;
+pub use keyexprs::FixedCapacityKeyExprs;
+pub use matching::*;
 pub use query::*;
 pub use resources::Resources;
 pub use response::*;
 pub use sample::*;
 pub use session::*;
-pub use zenoh_proto::{fields::Encoding, keyexpr};
+pub use zenoh_proto::{
+    SetIntersectionLevel,
+    exts::QueryTarget,
+    fields::{ConsolidationMode, Encoding},
+    keyexpr,
+};
@@ -2,7 +2,7 @@ use core::marker::PhantomData;
 
 use crate::{
     Query, ZConfig,
-    api::{Response, Sample},
+    api::{CompletionReason, MatchingStatus, Response, Sample},
 };
 
 pub trait ZArg {
@@ -14,6 +14,8 @@ pub trait ZArg {
 pub struct ResponseRef;
 pub struct SampleRef;
 pub struct QueryRef<'res, Config>(PhantomData<&'res Config>);
+pub struct MatchingStatusRef;
+pub struct CompletionReasonRef;
 
 impl ZArg for ResponseRef {
     type Of<'a> = &'a Response<'a>;
@@ -23,6 +25,21 @@ impl ZArg for SampleRef {
     type Of<'a> = &'a Sample<'a>;
 }
 
+/// A change of the matching status of the interest `interest`. Every listener gets it and only
+/// forwards the status of its own publisher or querier.
+pub struct MatchingUpdate {
+    pub(crate) interest: u32,
+    pub(crate) status: MatchingStatus,
+}
+
+impl ZArg for MatchingStatusRef {
+    type Of<'a> = &'a MatchingUpdate;
+}
+
+impl ZArg for CompletionReasonRef {
+    type Of<'a> = &'a CompletionReason;
+}
+
 impl<'res, Config> ZArg for QueryRef<'res, Config>
 where
     Config: ZConfig,
@@ -5,10 +5,16 @@ use dyn_utils::{
     storage::{RawOrBox, Storage},
 };
 use embassy_time::Instant;
-use heapless::FnvIndexMap;
+use heapless::{FnvIndexMap, String};
 use zenoh_proto::keyexpr;
 
-use crate::api::arg::{QueryRef, ResponseRef, SampleRef, ZArg};
+use crate::api::{
+    Query, ZConfig,
+    arg::{CompletionReasonRef, MatchingStatusRef, QueryRef, ResponseRef, SampleRef, ZArg},
+};
+
+mod trie;
+pub use trie::{TrieCallbacks, TrieQueryableCallbacks, TrieSubCallbacks};
 
 #[dyn_utils::dyn_trait(trait = ZDynCallback)]
 #[dyn_trait(dyn_utils::dyn_object)]
@@ -34,12 +40,14 @@ where
     fn insert(
         &mut self,
         id: u32,
-        ke: &'static keyexpr,
+        ke: &keyexpr,
         timedout: Option<Instant>,
         callback: DynCallback<'a, Self::Callback, Self::Future, Arg>,
     ) -> core::result::Result<(), crate::CollectionError>;
 
     fn drop_timedout(&mut self);
+    fn expired(&self) -> Option<u32>;
+    fn next_timedout(&self) -> Option<Instant>;
     fn get(&mut self, id: u32) -> Option<&mut DynCallback<'a, Self::Callback, Self::Future, Arg>>;
 
     fn remove(&mut self, id: u32) -> core::result::Result<(), crate::CollectionError>;
@@ -52,10 +60,18 @@ where
 
     fn decrease(&mut self, id: u32) -> bool;
 
+    fn keyexprs(&self) -> impl Iterator<Item = (u32, &keyexpr)>;
+
+    /// Callbacks whose key expression intersects `ke`, along with their id.
     fn intersects<'r>(
         &'r mut self,
         ke: &keyexpr,
-    ) -> impl Iterator<Item = &'r mut DynCallback<'a, Self::Callback, Self::Future, Arg>>
+    ) -> impl Iterator<
+        Item = (
+            u32,
+            &'r mut DynCallback<'a, Self::Callback, Self::Future, Arg>,
+        ),
+    >
     where
         DynCallback<'a, Self::Callback, Self::Future, Arg>: 'r;
 }
@@ -66,16 +82,22 @@ pub struct FixedCapacityCallbacks<
     const CAPACITY: usize,
     Callback: Storage,
     Future: Storage,
+    const MAX_KEYEXPR: usize = 64,
 > {
-    keyexprs: FnvIndexMap<u32, &'static keyexpr, CAPACITY>,
-    callbacks:
-        FnvIndexMap<(u32, &'static keyexpr), DynCallback<'a, Callback, Future, Arg>, CAPACITY>,
+    keyexprs: FnvIndexMap<u32, String<MAX_KEYEXPR>, CAPACITY>,
+    callbacks: FnvIndexMap<u32, DynCallback<'a, Callback, Future, Arg>, CAPACITY>,
     timedouts: FnvIndexMap<u32, Instant, CAPACITY>,
     counters: FnvIndexMap<u32, usize, CAPACITY>,
 }
 
-impl<'a, Arg: ZArg + 'a, const CAPACITY: usize, Callback: Storage, Future: Storage>
-    ZCallbacks<'a, Arg> for FixedCapacityCallbacks<'a, Arg, CAPACITY, Callback, Future>
+impl<
+    'a,
+    Arg: ZArg + 'a,
+    const CAPACITY: usize,
+    Callback: Storage,
+    Future: Storage,
+    const MAX_KEYEXPR: usize,
+> ZCallbacks<'a, Arg> for FixedCapacityCallbacks<'a, Arg, CAPACITY, Callback, Future, MAX_KEYEXPR>
 {
     type Callback = Callback;
     type Future = Future;
@@ -92,7 +114,7 @@ impl<'a, Arg: ZArg + 'a, const CAPACITY: usize, Callback: Storage, Future: Stora
     fn insert(
         &mut self,
         id: u32,
-        ke: &'static keyexpr,
+        ke: &keyexpr,
         timedout: Option<Instant>,
         callback: DynCallback<'a, Callback, Future, Arg>,
     ) -> core::result::Result<(), crate::CollectionError> {
@@ -100,7 +122,7 @@ impl<'a, Arg: ZArg + 'a, const CAPACITY: usize, Callback: Storage, Future: Stora
             return Err(crate::CollectionError::KeyAlreadyExists);
         }
 
-        if self.callbacks.contains_key(&(id, ke)) {
+        if self.callbacks.contains_key(&id) {
             return Err(crate::CollectionError::KeyAlreadyExists);
         }
 
@@ -108,12 +130,17 @@ impl<'a, Arg: ZArg + 'a, const CAPACITY: usize, Callback: Storage, Future: Stora
             return Err(crate::CollectionError::KeyAlreadyExists);
         }
 
+        let mut value = String::new();
+        value
+            .push_str(ke.as_str())
+            .map_err(|_| crate::CollectionError::CollectionTooSmall)?;
+
         self.keyexprs
-            .insert(id, ke)
+            .insert(id, value)
             .map_err(|_| crate::CollectionError::CollectionIsFull)?;
 
         self.callbacks
-            .insert((id, ke), callback)
+            .insert(id, callback)
             .map_err(|_| crate::CollectionError::CollectionIsFull)?;
 
         if let Some(timedout) = timedout {
@@ -128,9 +155,8 @@ impl<'a, Arg: ZArg + 'a, const CAPACITY: usize, Callback: Storage, Future: Stora
     fn drop_timedout(&mut self) {
         self.timedouts.retain(|id, timedout| {
             if Instant::now() >= *timedout {
-                if let Some(ke) = self.keyexprs.remove(id) {
-                    self.callbacks.remove(&(*id, ke));
-                }
+                self.keyexprs.remove(id);
+                self.callbacks.remove(id);
                 self.counters.remove(id);
 
                 false
@@ -140,10 +166,21 @@ impl<'a, Arg: ZArg + 'a, const CAPACITY: usize, Callback: Storage, Future: Stora
         });
     }
 
+    fn expired(&self) -> Option<u32> {
+        let now = Instant::now();
+        self.timedouts
+            .iter()
+            .find(|(_, timedout)| now >= **timedout)
+            .map(|(id, _)| *id)
+    }
+
+    fn next_timedout(&self) -> Option<Instant> {
+        self.timedouts.values().min().copied()
+    }
+
     fn remove(&mut self, id: u32) -> core::result::Result<(), crate::CollectionError> {
-        if let Some(ke) = self.keyexprs.remove(&id) {
-            self.callbacks.remove(&(id, ke));
-        }
+        self.keyexprs.remove(&id);
+        self.callbacks.remove(&id);
         self.timedouts.remove(&id);
         self.counters.remove(&id);
 
@@ -151,8 +188,7 @@ impl<'a, Arg: ZArg + 'a, const CAPACITY: usize, Callback: Storage, Future: Stora
     }
 
     fn get(&mut self, id: u32) -> Option<&mut DynCallback<'a, Callback, Future, Arg>> {
-        let ke = self.keyexprs.get(&id)?;
-        self.callbacks.get_mut(&(id, ke))
+        self.callbacks.get_mut(&id)
     }
 
     fn set_counter(
@@ -167,33 +203,42 @@ impl<'a, Arg: ZArg + 'a, const CAPACITY: usize, Callback: Storage, Future: Stora
     }
 
     fn decrease(&mut self, id: u32) -> bool {
-        if let Some(value) = self.counters.get_mut(&id) {
-            if *value > 0 {
-                *value -= 1;
-            }
+        let Some(value) = self.counters.get_mut(&id) else {
+            return false;
+        };
 
-            *value == 0
-        } else {
-            false
+        *value = value.saturating_sub(1);
+        if *value > 0 {
+            return false;
         }
+
+        // The last pending reply is done, the request id may be reused
+        self.counters.remove(&id);
+        true
+    }
+
+    fn keyexprs(&self) -> impl Iterator<Item = (u32, &keyexpr)> {
+        self.keyexprs
+            .iter()
+            .map(|(id, ke)| (*id, keyexpr::from_str_unchecked(ke.as_str())))
     }
 
     fn intersects<'r>(
         &'r mut self,
         ke: &keyexpr,
-    ) -> impl Iterator<Item = &'r mut DynCallback<'a, Callback, Future, Arg>>
+    ) -> impl Iterator<Item = (u32, &'r mut DynCallback<'a, Callback, Future, Arg>)>
     where
         DynCallback<'a, Callback, Future, Arg>: 'r,
     {
-        self.callbacks
-            .iter_mut()
-            .filter_map(move |((_, registered_ke), callback)| {
-                if registered_ke.intersects(ke) {
-                    Some(callback)
-                } else {
-                    None
-                }
-            })
+        let keyexprs = &self.keyexprs;
+        self.callbacks.iter_mut().filter_map(move |(id, callback)| {
+            let registered_ke = keyexpr::from_str_unchecked(keyexprs.get(id)?.as_str());
+            if registered_ke.intersects(ke) {
+                Some((*id, callback))
+            } else {
+                None
+            }
+        })
     }
 }
 
@@ -206,14 +251,16 @@ pub type FixedCapacityGetCallbacks<
     const CAPACITY: usize,
     Callback = RawOrBox<16>,
     Future = RawOrBox<128>,
-> = FixedCapacityCallbacks<'a, ResponseRef, CAPACITY, Callback, Future>;
+    const MAX_KEYEXPR: usize = 64,
+> = FixedCapacityCallbacks<'a, ResponseRef, CAPACITY, Callback, Future, MAX_KEYEXPR>;
 
 pub type FixedCapacitySubCallbacks<
     'a,
     const CAPACITY: usize,
     Callback = RawOrBox<16>,
     Future = RawOrBox<128>,
-> = FixedCapacityCallbacks<'a, SampleRef, CAPACITY, Callback, Future>;
+    const MAX_KEYEXPR: usize = 64,
+> = FixedCapacityCallbacks<'a, SampleRef, CAPACITY, Callback, Future, MAX_KEYEXPR>;
 
 pub type FixedCapacityQueryableCallbacks<
     'a,
@@ -221,7 +268,24 @@ pub type FixedCapacityQueryableCallbacks<
     const CAPACITY: usize,
     Callback = RawOrBox<16>,
     Future = RawOrBox<128>,
-> = FixedCapacityCallbacks<'a, QueryRef<'a, Config>, CAPACITY, Callback, Future>;
+    const MAX_KEYEXPR: usize = 128,
+> = FixedCapacityCallbacks<'a, QueryRef<'a, Config>, CAPACITY, Callback, Future, MAX_KEYEXPR>;
+
+pub type FixedCapacityMatchingCallbacks<
+    'a,
+    const CAPACITY: usize,
+    Callback = RawOrBox<16>,
+    Future = RawOrBox<128>,
+    const MAX_KEYEXPR: usize = 64,
+> = FixedCapacityCallbacks<'a, MatchingStatusRef, CAPACITY, Callback, Future, MAX_KEYEXPR>;
+
+pub type FixedCapacityCompletionCallbacks<
+    'a,
+    const CAPACITY: usize,
+    Callback = RawOrBox<16>,
+    Future = RawOrBox<128>,
+    const MAX_KEYEXPR: usize = 64,
+> = FixedCapacityCallbacks<'a, CompletionReasonRef, CAPACITY, Callback, Future, MAX_KEYEXPR>;
 
 pub struct SyncCallback<Arg, F>(F, PhantomData<Arg>);
 
@@ -263,6 +327,50 @@ where
     }
 }
 
+// `QueryRef::Of<'a>` requires `'res: 'a`, which a higher-ranked `Arg::Of<'_>` bound can only
+// satisfy for `'res = 'static`. Naming the `Query` directly lets the compiler use implied bounds.
+
+pub struct SyncQueryCallback<'res, Config, F>(F, PhantomData<&'res Config>);
+
+impl<Config, F> SyncQueryCallback<'_, Config, F> {
+    pub fn new(f: F) -> Self {
+        Self(f, PhantomData)
+    }
+}
+
+impl<'res, Config, F> ZCallback for SyncQueryCallback<'res, Config, F>
+where
+    Config: ZConfig,
+    F: FnMut(&Query<'_, 'res, Config>),
+{
+    type Arg = QueryRef<'res, Config>;
+
+    #[dyn_utils::sync]
+    async fn call(&mut self, arg: <Self::Arg as ZArg>::Of<'_>) {
+        (self.0)(arg)
+    }
+}
+
+pub struct AsyncQueryCallback<'res, Config, F>(F, PhantomData<&'res Config>);
+
+impl<Config, F> AsyncQueryCallback<'_, Config, F> {
+    pub fn new(f: F) -> Self {
+        Self(f, PhantomData)
+    }
+}
+
+impl<'res, Config, F> ZCallback for AsyncQueryCallback<'res, Config, F>
+where
+    Config: ZConfig,
+    F: AsyncFnMut(&Query<'_, 'res, Config>),
+{
+    type Arg = QueryRef<'res, Config>;
+
+    fn call(&mut self, arg: <Self::Arg as ZArg>::Of<'_>) -> impl Future<Output = ()> {
+        (self.0)(arg)
+    }
+}
+
 #[test]
 fn test() {
     use super::Response;
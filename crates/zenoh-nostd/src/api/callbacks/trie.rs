@@ -0,0 +1,548 @@
+use core::cell::Cell;
+
+use dyn_utils::storage::{RawOrBox, Storage};
+use embassy_time::Instant;
+use heapless::{FnvIndexMap, String, Vec};
+use zenoh_proto::keyexpr;
+
+use crate::api::{
+    arg::{QueryRef, SampleRef, ZArg},
+    callbacks::{DynCallback, FixedCapacityCallbacks, ZCallbacks},
+};
+
+struct Node<const MAX_CHUNK: usize> {
+    chunk: String<MAX_CHUNK>,
+    parent: Option<u16>,
+    first_child: Option<u16>,
+    next_sibling: Option<u16>,
+    refs: usize,
+    matched: Cell<bool>,
+}
+
+/// A `ZCallbacks` that indexes key expressions in a trie of chunks, so that matching an incoming
+/// key only walks the branches that can intersect it instead of every registered key.
+pub struct TrieCallbacks<
+    'a,
+    Arg: ZArg,
+    const CAPACITY: usize,
+    Callback: Storage,
+    Future: Storage,
+    const NODES: usize = 64,
+    const MAX_CHUNK: usize = 32,
+    const MAX_KEYEXPR: usize = 64,
+> {
+    inner: FixedCapacityCallbacks<'a, Arg, CAPACITY, Callback, Future, MAX_KEYEXPR>,
+    nodes: Vec<Option<Node<MAX_CHUNK>>, NODES>,
+    roots: Option<u16>,
+    leaves: FnvIndexMap<u32, u16, CAPACITY>,
+}
+
+fn split(ke: &str) -> (&str, &str) {
+    ke.split_once('/').unwrap_or((ke, ""))
+}
+
+fn is_verbatim(chunk: &str) -> bool {
+    chunk.starts_with('@')
+}
+
+impl<
+    'a,
+    Arg: ZArg,
+    const CAPACITY: usize,
+    Callback: Storage,
+    Future: Storage,
+    const NODES: usize,
+    const MAX_CHUNK: usize,
+    const MAX_KEYEXPR: usize,
+> TrieCallbacks<'a, Arg, CAPACITY, Callback, Future, NODES, MAX_CHUNK, MAX_KEYEXPR>
+{
+    fn node(&self, index: u16) -> &Node<MAX_CHUNK> {
+        self.nodes[index as usize]
+            .as_ref()
+            .expect("trie links point to live nodes")
+    }
+
+    fn node_mut(&mut self, index: u16) -> &mut Node<MAX_CHUNK> {
+        self.nodes[index as usize]
+            .as_mut()
+            .expect("trie links point to live nodes")
+    }
+
+    fn children(&self, parent: Option<u16>) -> Option<u16> {
+        match parent {
+            Some(parent) => self.node(parent).first_child,
+            None => self.roots,
+        }
+    }
+
+    fn set_children(&mut self, parent: Option<u16>, first: Option<u16>) {
+        match parent {
+            Some(parent) => self.node_mut(parent).first_child = first,
+            None => self.roots = first,
+        }
+    }
+
+    fn child(&self, parent: Option<u16>, chunk: &str) -> Option<u16> {
+        let mut child = self.children(parent);
+        while let Some(index) = child {
+            let node = self.node(index);
+            if node.chunk.as_str() == chunk {
+                return Some(index);
+            }
+
+            child = node.next_sibling;
+        }
+
+        None
+    }
+
+    fn alloc(
+        &mut self,
+        parent: Option<u16>,
+        chunk: &str,
+    ) -> core::result::Result<u16, crate::CollectionError> {
+        let mut value = String::new();
+        value
+            .push_str(chunk)
+            .map_err(|_| crate::CollectionError::CollectionTooSmall)?;
+
+        let node = Node {
+            chunk: value,
+            parent,
+            first_child: None,
+            next_sibling: self.children(parent),
+            refs: 0,
+            matched: Cell::new(false),
+        };
+
+        let index = match self.nodes.iter().position(Option::is_none) {
+            Some(index) => {
+                self.nodes[index] = Some(node);
+                index
+            }
+            None => {
+                self.nodes
+                    .push(Some(node))
+                    .map_err(|_| crate::CollectionError::CollectionIsFull)?;
+                self.nodes.len() - 1
+            }
+        };
+
+        let index = index as u16;
+        self.set_children(parent, Some(index));
+
+        Ok(index)
+    }
+
+    /// Drops one reference on `leaf` and each of its ancestors, freeing unused nodes.
+    fn release(&mut self, leaf: Option<u16>) {
+        let mut current = leaf;
+        while let Some(index) = current {
+            let node = self.node_mut(index);
+            node.refs -= 1;
+            current = node.parent;
+
+            if node.refs == 0 {
+                self.unlink(index);
+                self.nodes[index as usize] = None;
+            }
+        }
+    }
+
+    fn unlink(&mut self, index: u16) {
+        let (parent, next) = {
+            let node = self.node(index);
+            (node.parent, node.next_sibling)
+        };
+
+        let mut child = self.children(parent);
+        if child == Some(index) {
+            self.set_children(parent, next);
+            return;
+        }
+
+        while let Some(sibling) = child {
+            let node = self.node_mut(sibling);
+            if node.next_sibling == Some(index) {
+                node.next_sibling = next;
+                return;
+            }
+
+            child = node.next_sibling;
+        }
+    }
+
+    /// Marks every node whose path from the root intersects the chunks of `rest` consumed so far.
+    fn walk(&self, at: Option<u16>, rest: &str) {
+        if rest.is_empty() {
+            if let Some(index) = at {
+                self.node(index).matched.set(true);
+            }
+
+            // A trailing `**` also matches zero chunks
+            if let Some(index) = self.child(at, "**") {
+                self.walk(Some(index), rest);
+            }
+
+            return;
+        }
+
+        let (chunk, next) = split(rest);
+        if chunk == "**" {
+            self.walk(at, next);
+        }
+
+        let mut child = self.children(at);
+        while let Some(index) = child {
+            let node = self.node(index);
+            let registered = node.chunk.as_str();
+
+            if registered == "**" {
+                let mut suffix = rest;
+                loop {
+                    self.walk(Some(index), suffix);
+
+                    let (absorbed, next) = split(suffix);
+                    if suffix.is_empty() || is_verbatim(absorbed) {
+                        break;
+                    }
+
+                    suffix = next;
+                }
+            } else if chunk == "**" {
+                if !is_verbatim(registered) {
+                    self.walk(Some(index), rest);
+                }
+            } else if keyexpr::from_str_unchecked(registered)
+                .intersects(keyexpr::from_str_unchecked(chunk))
+            {
+                self.walk(Some(index), next);
+            }
+
+            child = node.next_sibling;
+        }
+    }
+}
+
+impl<
+    'a,
+    Arg: ZArg + 'a,
+    const CAPACITY: usize,
+    Callback: Storage,
+    Future: Storage,
+    const NODES: usize,
+    const MAX_CHUNK: usize,
+    const MAX_KEYEXPR: usize,
+> ZCallbacks<'a, Arg>
+    for TrieCallbacks<'a, Arg, CAPACITY, Callback, Future, NODES, MAX_CHUNK, MAX_KEYEXPR>
+{
+    type Callback = Callback;
+    type Future = Future;
+
+    fn empty() -> Self {
+        Self {
+            inner: FixedCapacityCallbacks::empty(),
+            nodes: Vec::new(),
+            roots: None,
+            leaves: FnvIndexMap::new(),
+        }
+    }
+
+    fn insert(
+        &mut self,
+        id: u32,
+        ke: &keyexpr,
+        timedout: Option<Instant>,
+        callback: DynCallback<'a, Callback, Future, Arg>,
+    ) -> core::result::Result<(), crate::CollectionError> {
+        if self.leaves.contains_key(&id) {
+            return Err(crate::CollectionError::KeyAlreadyExists);
+        }
+
+        let mut leaf = None;
+        for chunk in ke.chunks() {
+            let index = match self.child(leaf, chunk) {
+                Some(index) => index,
+                None => match self.alloc(leaf, chunk) {
+                    Ok(index) => index,
+                    Err(e) => {
+                        self.release(leaf);
+                        return Err(e);
+                    }
+                },
+            };
+
+            self.node_mut(index).refs += 1;
+            leaf = Some(index);
+        }
+
+        let Some(index) = leaf else {
+            return Err(crate::CollectionError::KeyNotFound);
+        };
+
+        if let Err(e) = self.inner.insert(id, ke, timedout, callback) {
+            self.release(leaf);
+            return Err(e);
+        }
+
+        if self.leaves.insert(id, index).is_err() {
+            self.release(leaf);
+            let _ = self.inner.remove(id);
+            return Err(crate::CollectionError::CollectionIsFull);
+        }
+
+        Ok(())
+    }
+
+    fn drop_timedout(&mut self) {
+        while let Some(id) = self.inner.expired() {
+            let _ = self.remove(id);
+        }
+    }
+
+    fn expired(&self) -> Option<u32> {
+        self.inner.expired()
+    }
+
+    fn next_timedout(&self) -> Option<Instant> {
+        self.inner.next_timedout()
+    }
+
+    fn get(&mut self, id: u32) -> Option<&mut DynCallback<'a, Callback, Future, Arg>> {
+        self.inner.get(id)
+    }
+
+    fn remove(&mut self, id: u32) -> core::result::Result<(), crate::CollectionError> {
+        if let Some(leaf) = self.leaves.remove(&id) {
+            self.release(Some(leaf));
+        }
+
+        self.inner.remove(id)
+    }
+
+    fn set_counter(
+        &mut self,
+        id: u32,
+        value: usize,
+    ) -> core::result::Result<(), crate::CollectionError> {
+        self.inner.set_counter(id, value)
+    }
+
+    fn decrease(&mut self, id: u32) -> bool {
+        self.inner.decrease(id)
+    }
+
+    fn keyexprs(&self) -> impl Iterator<Item = (u32, &keyexpr)> {
+        self.inner.keyexprs()
+    }
+
+    fn intersects<'r>(
+        &'r mut self,
+        ke: &keyexpr,
+    ) -> impl Iterator<Item = (u32, &'r mut DynCallback<'a, Callback, Future, Arg>)>
+    where
+        DynCallback<'a, Callback, Future, Arg>: 'r,
+    {
+        for node in self.nodes.iter().flatten() {
+            node.matched.set(false);
+        }
+
+        self.walk(None, ke.as_str());
+
+        let nodes = &self.nodes;
+        let leaves = &self.leaves;
+        self.inner
+            .callbacks
+            .iter_mut()
+            .filter_map(move |(id, callback)| {
+                let node = nodes[*leaves.get(id)? as usize].as_ref()?;
+                node.matched.get().then_some((*id, callback))
+            })
+    }
+}
+
+pub type TrieSubCallbacks<
+    'a,
+    const CAPACITY: usize,
+    Callback = RawOrBox<16>,
+    Future = RawOrBox<128>,
+    const NODES: usize = 64,
+    const MAX_CHUNK: usize = 32,
+    const MAX_KEYEXPR: usize = 64,
+> = TrieCallbacks<'a, SampleRef, CAPACITY, Callback, Future, NODES, MAX_CHUNK, MAX_KEYEXPR>;
+
+pub type TrieQueryableCallbacks<
+    'a,
+    Config,
+    const CAPACITY: usize,
+    Callback = RawOrBox<16>,
+    Future = RawOrBox<128>,
+    const NODES: usize = 64,
+    const MAX_CHUNK: usize = 32,
+    const MAX_KEYEXPR: usize = 128,
+> = TrieCallbacks<
+    'a,
+    QueryRef<'a, Config>,
+    CAPACITY,
+    Callback,
+    Future,
+    NODES,
+    MAX_CHUNK,
+    MAX_KEYEXPR,
+>;
+
+#[cfg(test)]
+fn callback() -> DynCallback<'static, RawOrBox<16>, RawOrBox<128>, SampleRef> {
+    dyn_utils::DynObject::new(crate::api::callbacks::SyncCallback::<SampleRef, _>::new(
+        |_: &crate::Sample<'_>| {},
+    ))
+}
+
+#[cfg(test)]
+fn matching<'a>(
+    callbacks: &mut impl ZCallbacks<'a, SampleRef, Callback = RawOrBox<16>, Future = RawOrBox<128>>,
+    ke: &str,
+) -> Vec<u32, 16> {
+    let mut ids: Vec<u32, 16> = callbacks
+        .intersects(keyexpr::new(ke).unwrap())
+        .map(|(id, _)| id)
+        .collect();
+    ids.sort_unstable();
+    ids
+}
+
+#[test]
+fn trie_matching() {
+    const REGISTERED: [&str; 16] = [
+        "a/b/c", "a/*/c", "a/**", "**", "a/b$*", "a/$*b/c", "a/**/c", "@a/b", "a/@b/**", "**/c",
+        "a/b/**/d", "*/b", "@a/**", "a/*", "a/**/@b", "*/x$*",
+    ];
+    const QUERIES: [&str; 24] = [
+        "a",
+        "a/b",
+        "a/b/c",
+        "a/xb/c",
+        "a/bb",
+        "a/@b",
+        "a/@b/c",
+        "@a/b",
+        "@a",
+        "@a/b/c",
+        "a/b/c/d",
+        "x/b",
+        "**",
+        "a/**",
+        "*/*",
+        "a/*/c",
+        "a/b$*",
+        "c",
+        "a/b/x/y/d",
+        "**/@b/**",
+        "@a/**",
+        "a/**/@b",
+        "x$*/c",
+        "a/x$*",
+    ];
+
+    let mut trie = TrieCallbacks::<SampleRef, 16, RawOrBox<16>, RawOrBox<128>, 128>::empty();
+    let mut flat = FixedCapacityCallbacks::<SampleRef, 16, RawOrBox<16>, RawOrBox<128>>::empty();
+
+    for (id, ke) in REGISTERED.iter().enumerate() {
+        let ke = keyexpr::new(ke).unwrap();
+        trie.insert(id as u32, ke, None, callback()).unwrap();
+        flat.insert(id as u32, ke, None, callback()).unwrap();
+    }
+
+    for query in QUERIES {
+        let expected: Vec<u32, 16> = REGISTERED
+            .iter()
+            .enumerate()
+            .filter(|(_, ke)| {
+                keyexpr::new(ke)
+                    .unwrap()
+                    .intersects(keyexpr::new(query).unwrap())
+            })
+            .map(|(id, _)| id as u32)
+            .collect();
+
+        assert_eq!(matching(&mut flat, query), expected, "{query}");
+        assert_eq!(matching(&mut trie, query), expected, "{query}");
+    }
+}
+
+#[test]
+fn trie_node_reuse() {
+    let mut trie = TrieCallbacks::<SampleRef, 4, RawOrBox<16>, RawOrBox<128>, 8>::empty();
+    let live = |trie: &TrieCallbacks<SampleRef, 4, RawOrBox<16>, RawOrBox<128>, 8>| {
+        trie.nodes.iter().flatten().count()
+    };
+
+    trie.insert(1, keyexpr::new("a/b/c").unwrap(), None, callback())
+        .unwrap();
+    trie.insert(2, keyexpr::new("a/b/d").unwrap(), None, callback())
+        .unwrap();
+    assert_eq!(live(&trie), 4);
+    assert_eq!(
+        trie.insert(2, keyexpr::new("x").unwrap(), None, callback()),
+        Err(crate::CollectionError::KeyAlreadyExists)
+    );
+
+    // Only the leaf of `a/b/c` is freed, `a/b` is still used by `a/b/d`
+    trie.remove(1).unwrap();
+    assert_eq!(live(&trie), 3);
+    assert!(matching(&mut trie, "a/b/c").is_empty());
+    assert_eq!(matching(&mut trie, "a/b/*"), [2]);
+
+    // The freed slot is reused
+    trie.insert(3, keyexpr::new("a/b/c").unwrap(), None, callback())
+        .unwrap();
+    assert_eq!((live(&trie), trie.nodes.len()), (4, 4));
+    assert_eq!(matching(&mut trie, "a/b/*"), [2, 3]);
+
+    trie.remove(2).unwrap();
+    trie.remove(3).unwrap();
+    assert_eq!(live(&trie), 0);
+    assert!(trie.roots.is_none());
+    assert!(matching(&mut trie, "**").is_empty());
+
+    trie.insert(4, keyexpr::new("a/b/c").unwrap(), None, callback())
+        .unwrap();
+    assert_eq!((live(&trie), trie.nodes.len()), (3, 4));
+    assert_eq!(matching(&mut trie, "a/**"), [4]);
+}
+
+#[test]
+fn trie_capacity() {
+    let mut trie = TrieCallbacks::<SampleRef, 2, RawOrBox<16>, RawOrBox<128>, 4, 4>::empty();
+
+    trie.insert(1, keyexpr::new("a/b/c").unwrap(), None, callback())
+        .unwrap();
+
+    // Out of nodes, the shared `a/b` prefix is released again
+    assert_eq!(
+        trie.insert(2, keyexpr::new("a/b/x/y").unwrap(), None, callback()),
+        Err(crate::CollectionError::CollectionIsFull)
+    );
+    // A chunk longer than `MAX_CHUNK`
+    assert_eq!(
+        trie.insert(2, keyexpr::new("a/chunk").unwrap(), None, callback()),
+        Err(crate::CollectionError::CollectionTooSmall)
+    );
+    assert_eq!(trie.nodes.iter().flatten().count(), 3);
+    assert_eq!(matching(&mut trie, "a/**"), [1]);
+
+    trie.insert(2, keyexpr::new("a/b/x").unwrap(), None, callback())
+        .unwrap();
+
+    // Out of callback slots, the nodes are released
+    assert_eq!(
+        trie.insert(3, keyexpr::new("a/y").unwrap(), None, callback()),
+        Err(crate::CollectionError::CollectionIsFull)
+    );
+    assert_eq!(trie.nodes.iter().flatten().count(), 4);
+    assert_eq!(matching(&mut trie, "a/**"), [1, 2]);
+
+    trie.remove(1).unwrap();
+    trie.remove(2).unwrap();
+    assert_eq!(trie.nodes.iter().flatten().count(), 0);
+}
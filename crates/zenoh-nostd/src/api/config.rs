@@ -1,11 +1,55 @@
 use crate::{
     api::{
-        arg::{QueryRef, ResponseRef, SampleRef},
+        arg::{CompletionReasonRef, MatchingStatusRef, QueryRef, ResponseRef, SampleRef},
         callbacks::ZCallbacks,
+        consolidation::ZConsolidation,
+        interests::ZInterests,
+        keyexprs::ZKeyExprs,
     },
     platform::ZPlatform,
 };
 
+use embassy_time::Duration;
+use sha3::{
+    Shake128,
+    digest::{ExtendableOutput, Update, XofReader},
+};
+use zenoh_proto::fields::{Resolution, ZenohIdProto};
+
+pub use crate::io::transport::Credentials;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Identity<'a> {
+    /// A ZenohId drawn from `ZPlatform::fill_random`.
+    Random,
+    /// The little-endian bytes of the ZenohId, at most 16 bytes.
+    Explicit(&'a [u8]),
+    /// A ZenohId hashed from a stable seed such as a MAC address or a serial number.
+    Derived(&'a [u8]),
+}
+
+impl Identity<'_> {
+    pub(crate) fn zid(&self, platform: &impl ZPlatform) -> crate::ZResult<ZenohIdProto> {
+        let mut bytes = [0u8; 16];
+
+        let bytes = match self {
+            Identity::Random => {
+                platform.fill_random(&mut bytes)?;
+                &bytes[..]
+            }
+            Identity::Explicit(bytes) => bytes,
+            Identity::Derived(seed) => {
+                let mut hasher = Shake128::default();
+                hasher.update(seed);
+                hasher.finalize_xof().read(&mut bytes);
+                &bytes[..]
+            }
+        };
+
+        Ok(ZenohIdProto::try_from(bytes)?)
+    }
+}
+
 pub trait ZConfig
 where
     Self: Sized + 'static,
@@ -15,6 +59,12 @@ where
     type GetCallbacks<'res>: ZCallbacks<'res, ResponseRef>;
     type SubCallbacks<'res>: ZCallbacks<'res, SampleRef>;
     type QueryableCallbacks<'res>: ZCallbacks<'res, QueryRef<'res, Self>>;
+    type MatchingCallbacks<'res>: ZCallbacks<'res, MatchingStatusRef>;
+    type CompletionCallbacks<'res>: ZCallbacks<'res, CompletionReasonRef>;
+
+    type KeyExprs: ZKeyExprs;
+    type Interests: ZInterests;
+    type Consolidation: ZConsolidation;
 
     type TxBuf: AsMut<[u8]>;
     type RxBuf: AsMut<[u8]>;
@@ -24,4 +74,55 @@ where
     fn txrx(&mut self) -> (&mut Self::TxBuf, &mut Self::RxBuf);
 
     fn into_parts(self) -> (Self::Platform, Self::TxBuf, Self::RxBuf);
+
+    fn identity(&self) -> Identity<'_> {
+        Identity::Random
+    }
+
+    fn lease(&self) -> Duration {
+        Duration::from_secs(20)
+    }
+
+    /// Number of keep-alives sent per lease period, must be at least 1.
+    fn keep_alive(&self) -> usize {
+        4
+    }
+
+    fn open_timeout(&self) -> Duration {
+        Duration::from_secs(5)
+    }
+
+    fn batch_size(&self) -> u16 {
+        u16::MAX
+    }
+
+    /// Proposed resolution of frame SNs and request IDs, 64 bits are downgraded to 32 bits.
+    fn resolution(&self) -> Resolution {
+        Resolution::default()
+    }
+
+    fn credentials(&self) -> Option<Credentials> {
+        None
+    }
+
+    fn lowlatency(&self) -> bool {
+        false
+    }
+
+    /// Negotiate LZ4 batch compression, only available with the `compression` feature.
+    fn compression(&self) -> bool {
+        cfg!(feature = "compression")
+    }
+
+    /// Scratch buffers of LZ4 batch compression, each at least as large as a batch. Compression
+    /// is not negotiated without them.
+    #[cfg(feature = "compression")]
+    fn compression_bufs(&mut self) -> Option<(&'static mut [u8], &'static mut [u8])> {
+        None
+    }
+
+    /// Stamp puts with the session HLC and track the timestamps of incoming samples.
+    fn timestamping(&self) -> bool {
+        false
+    }
 }
@@ -0,0 +1,119 @@
+use embassy_time::Instant;
+use heapless::{FnvIndexMap, String};
+use zenoh_proto::{fields::Timestamp, keyexpr};
+
+/// `ConsolidationMode::Monotonic` consolidation of the replies of a get: replies are delivered as
+/// they arrive, unless a newer one for the same key was already delivered.
+pub trait ZConsolidation {
+    fn empty() -> Self;
+
+    fn insert(
+        &mut self,
+        rid: u32,
+        timedout: Option<Instant>,
+    ) -> core::result::Result<(), crate::CollectionError>;
+
+    fn drop_timedout(&mut self);
+
+    /// Returns `false` if a reply for the same key with a newer timestamp was already delivered.
+    fn accept(&mut self, rid: u32, ke: &keyexpr, timestamp: Option<&Timestamp>) -> bool;
+
+    fn remove(&mut self, rid: u32) -> bool;
+}
+
+struct PendingRequest<const KEYS: usize, const MAX_KEYEXPR: usize> {
+    timedout: Option<Instant>,
+    latest: FnvIndexMap<u64, (String<MAX_KEYEXPR>, Timestamp), KEYS>,
+}
+
+pub struct FixedCapacityConsolidation<
+    const REQUESTS: usize,
+    const KEYS: usize = 16,
+    const MAX_KEYEXPR: usize = 64,
+> {
+    requests: FnvIndexMap<u32, PendingRequest<KEYS, MAX_KEYEXPR>, REQUESTS>,
+}
+
+fn hash(ke: &keyexpr) -> u64 {
+    // FNV-1a
+    ke.as_str()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+}
+
+impl<const REQUESTS: usize, const KEYS: usize, const MAX_KEYEXPR: usize> ZConsolidation
+    for FixedCapacityConsolidation<REQUESTS, KEYS, MAX_KEYEXPR>
+{
+    fn empty() -> Self {
+        Self {
+            requests: FnvIndexMap::new(),
+        }
+    }
+
+    fn insert(
+        &mut self,
+        rid: u32,
+        timedout: Option<Instant>,
+    ) -> core::result::Result<(), crate::CollectionError> {
+        if self.requests.contains_key(&rid) {
+            return Err(crate::CollectionError::KeyAlreadyExists);
+        }
+
+        let request = PendingRequest {
+            timedout,
+            latest: FnvIndexMap::new(),
+        };
+
+        self.requests
+            .insert(rid, request)
+            .map_err(|_| crate::CollectionError::CollectionIsFull)
+            .map(|_| ())
+    }
+
+    fn drop_timedout(&mut self) {
+        let now = Instant::now();
+        self.requests
+            .retain(|_, request| request.timedout.is_none_or(|timedout| now < timedout));
+    }
+
+    fn accept(&mut self, rid: u32, ke: &keyexpr, timestamp: Option<&Timestamp>) -> bool {
+        let Some(request) = self.requests.get_mut(&rid) else {
+            return true;
+        };
+
+        // Replies without a timestamp can't be ordered
+        let Some(timestamp) = timestamp else {
+            return true;
+        };
+
+        let hash = hash(ke);
+        match request.latest.get_mut(&hash) {
+            // Neither can replies whose key collides with an other one
+            Some((key, _)) if key.as_str() != ke.as_str() => true,
+            Some((_, latest)) if *latest >= *timestamp => false,
+            Some((_, latest)) => {
+                *latest = *timestamp;
+                true
+            }
+            None => {
+                let mut key = String::new();
+                if key.push_str(ke.as_str()).is_err()
+                    || request.latest.insert(hash, (key, *timestamp)).is_err()
+                {
+                    crate::warn!(
+                        "{}: Couldn't store the key for consolidation, forwarding the reply as-is",
+                        crate::zctx!()
+                    );
+                }
+
+                true
+            }
+        }
+    }
+
+    fn remove(&mut self, rid: u32) -> bool {
+        self.requests.remove(&rid).is_some()
+    }
+}
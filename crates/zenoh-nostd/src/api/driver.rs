@@ -7,7 +7,7 @@ use core::ops::{Deref, DerefMut};
 use embassy_futures::select::{Either, select};
 use embassy_sync::{blocking_mutex::raw::NoopRawMutex, mutex::Mutex};
 use embassy_time::{Instant, Timer};
-use zenoh_proto::msgs::KeepAlive;
+use zenoh_proto::{fields::Resolution, msgs::KeepAlive};
 
 use crate::{
     api::{ZConfig, resources::SessionResources},
@@ -21,6 +21,7 @@ where
     pub(crate) tx_buf: Config::TxBuf,
     pub(crate) tx: TransportTx<'transport, Config::Platform>,
     pub(crate) sn: u32,
+    pub(crate) resolution: Resolution,
 
     pub(crate) next_keepalive: Instant,
     pub(crate) config: TransportMineConfig,
@@ -32,6 +33,7 @@ where
 {
     pub(crate) rx_buf: Config::RxBuf,
     pub(crate) rx: TransportRx<'transport, Config::Platform>,
+    pub(crate) resolution: Resolution,
 
     pub(crate) last_read: Instant,
     pub(crate) config: TransportOtherConfig,
@@ -68,15 +70,41 @@ where
         let mut rx_guard = self.rx.lock().await;
         let rx = rx_guard.deref_mut();
 
+        // A batch may be read in several chunks, so `recv` is never raced against the timers
+        match select(self.rx_loop(rx, resources), self.timer_loop(resources)).await {
+            Either::First(res) | Either::Second(res) => res,
+        }
+    }
+
+    async fn rx_loop(
+        &self,
+        rx: &mut DriverRx<'res, Config>,
+        resources: &SessionResources<'res, Config>,
+    ) -> crate::ZResult<()> {
+        loop {
+            let msg = rx.recv().await?;
+            self.update(msg, resources).await?;
+        }
+    }
+
+    async fn timer_loop(&self, resources: &SessionResources<'res, Config>) -> crate::ZResult<()> {
         loop {
-            let write_lease = {
+            let next_keepalive = {
                 let tx_guard = self.tx.lock().await;
                 let tx = tx_guard.deref();
-                Timer::at(tx.next_keepalive())
+                tx.next_keepalive()
             };
 
-            match select(write_lease, rx.recv()).await {
+            // Wake up for whichever comes first: the keepalive or the expiry of a pending get
+            let deadline = match resources.next_get_timedout().await {
+                Some(timedout) => timedout.min(next_keepalive),
+                None => next_keepalive,
+            };
+
+            match select(Timer::at(deadline), resources.get_inserted.wait()).await {
                 Either::First(_) => {
+                    resources.expire_gets().await;
+
                     let mut tx_guard = self.tx.lock().await;
                     let tx = tx_guard.deref_mut();
                     if Instant::now() >= tx.next_keepalive() {
@@ -85,9 +113,8 @@ where
                         tx.unframed(KeepAlive {}).await?;
                     }
                 }
-                Either::Second(msg) => {
-                    self.update(msg?, resources).await?;
-                }
+                // A new get may expire before the current deadline
+                Either::Second(_) => {}
             }
         }
     }
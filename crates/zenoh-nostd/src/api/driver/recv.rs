@@ -1,13 +1,18 @@
 use embassy_futures::select::{Either, select};
 use embassy_time::Timer;
 
+use zenoh_proto::{BatchReader, fields::Field};
+
 use crate::{api::ZConfig, io::transport::ZTransportRx};
 
 impl<'transport, Config> super::DriverRx<'transport, Config>
 where
     Config: ZConfig,
 {
-    pub async fn recv(&mut self) -> crate::ZResult<&[u8]> {
+    pub async fn recv(&mut self) -> crate::ZResult<BatchReader<'_, &[u8]>> {
+        let lowlatency = self.rx.lowlatency();
+        let resolution = self.resolution.get(Field::FrameSN);
+
         let read_lease = Timer::at(self.last_read + self.config.other_lease);
 
         match select(read_lease, self.rx.recv(self.rx_buf.as_mut())).await {
@@ -18,7 +23,9 @@ where
             Either::Second(msg) => match msg {
                 Ok(msg) => {
                     self.last_read = embassy_time::Instant::now();
-                    Ok(msg)
+                    Ok(BatchReader::new(msg)
+                        .lowlatency(lowlatency)
+                        .resolution(resolution))
                 }
                 Err(e) => crate::zbail!(e),
             },
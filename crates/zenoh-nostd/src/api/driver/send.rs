@@ -11,10 +11,15 @@ where
 {
     pub async fn framed(&mut self, x: impl ZFramed) -> crate::ZResult<()> {
         self.tx
-            .send(self.tx_buf.as_mut(), &mut self.sn, |batch| {
-                batch.framed(&x, Reliability::Reliable, QoS::default())?;
-                Ok(())
-            })
+            .send(
+                self.tx_buf.as_mut(),
+                &mut self.sn,
+                self.resolution.get(Field::FrameSN),
+                |batch| {
+                    batch.framed(&x, Reliability::Reliable, QoS::default())?;
+                    Ok(())
+                },
+            )
             .await?;
 
         self.next_keepalive =
@@ -25,10 +30,15 @@ where
 
     pub async fn unframed(&mut self, x: impl ZUnframed) -> crate::ZResult<()> {
         self.tx
-            .send(self.tx_buf.as_mut(), &mut self.sn, |batch| {
-                batch.unframed(&x)?;
-                Ok(())
-            })
+            .send(
+                self.tx_buf.as_mut(),
+                &mut self.sn,
+                self.resolution.get(Field::FrameSN),
+                |batch| {
+                    batch.unframed(&x)?;
+                    Ok(())
+                },
+            )
             .await?;
 
         self.next_keepalive =
@@ -1,24 +1,36 @@
-use zenoh_proto::{exts::Value, msgs::*, *};
+use zenoh_proto::{
+    exts::Value,
+    fields::{Timestamp, WireExpr},
+    msgs::*,
+    *,
+};
 
 use crate::{
-    Sample,
+    CompletionReason, MatchingStatus, Sample,
     api::{
         ZConfig,
+        arg::MatchingUpdate,
         callbacks::{ZCallbacks, ZDynCallback},
+        consolidation::ZConsolidation,
+        interests::{EntityKind, ZInterests},
+        keyexprs::ZKeyExprs,
         resources::SessionResources,
     },
 };
 
+/// Upper bound for a key expression resolved from a declared scope and a suffix.
+const MAX_RESOLVED_KEYEXPR: usize = 256;
+
 impl<'res, Config> super::Driver<'res, Config>
 where
     Config: ZConfig,
 {
     pub(crate) async fn update(
         &self,
-        reader: &[u8],
+        batch: BatchReader<'_, &[u8]>,
         resources: &SessionResources<'res, Config>,
     ) -> crate::ZResult<()> {
-        let batch = BatchReader::new(reader);
+        let mut scratch = [0u8; MAX_RESOLVED_KEYEXPR];
 
         for msg in batch {
             match msg {
@@ -28,18 +40,19 @@ where
                 Message::Push {
                     body:
                         Push {
-                            wire_expr,
-                            payload: PushBody::Put(Put { payload, .. }),
-                            ..
+                            wire_expr, payload, ..
                         },
                     ..
                 } => {
-                    let ke = wire_expr.suffix;
-                    let ke = keyexpr::new(ke)?;
-                    let sample = Sample::new(ke, payload);
+                    let Ok(ke) = resources.resolve(&wire_expr, &mut scratch).await else {
+                        crate::warn!("{}: Couldn't resolve the wire expression", crate::zctx!());
+                        continue;
+                    };
+                    let (sample, timestamp) = Self::sample(ke, payload);
+                    resources.update_hlc(timestamp.as_ref());
 
                     let mut sub_cb = resources.sub_callbacks.lock().await;
-                    for cb in sub_cb.intersects(ke) {
+                    for (_, cb) in sub_cb.intersects(ke) {
                         cb.call(&sample).await;
                     }
                 }
@@ -53,18 +66,33 @@ where
                         },
                     ..
                 } => {
-                    let ke = wire_expr.suffix;
-                    let ke = keyexpr::new(ke)?;
-                    let response = match payload {
-                        ResponseBody::Reply(Reply {
-                            payload: PushBody::Put(Put { payload, .. }),
-                            ..
-                        }) => crate::Response::Ok(crate::Sample::new(ke, payload)),
-                        ResponseBody::Err(Err { payload, .. }) => {
-                            crate::Response::Err(crate::Sample::new(ke, payload))
+                    let Ok(ke) = resources.resolve(&wire_expr, &mut scratch).await else {
+                        crate::warn!("{}: Couldn't resolve the wire expression", crate::zctx!());
+                        continue;
+                    };
+                    let (response, timestamp) = match payload {
+                        ResponseBody::Reply(Reply { payload, .. }) => {
+                            let (sample, timestamp) = Self::sample(ke, payload);
+                            resources.update_hlc(timestamp.as_ref());
+                            (crate::Response::Ok(sample), timestamp)
                         }
+                        ResponseBody::Err(Err {
+                            encoding, payload, ..
+                        }) => (
+                            crate::Response::Err(Sample::new(ke, payload).with_encoding(encoding)),
+                            None,
+                        ),
                     };
 
+                    if !resources
+                        .consolidation
+                        .lock()
+                        .await
+                        .accept(rid, ke, timestamp.as_ref())
+                    {
+                        continue;
+                    }
+
                     let mut get_cb = resources.get_callbacks.lock().await;
                     if let Some(cb) = get_cb.get(rid) {
                         cb.call(&response).await;
@@ -74,10 +102,9 @@ where
                     body: ResponseFinal { rid, .. },
                     ..
                 } => {
-                    let mut res = resources.get_callbacks.lock().await;
-                    res.remove(rid)?;
-
-                    // TODO: also close channels
+                    resources
+                        .complete_get(rid, false, CompletionReason::Final)
+                        .await;
                 }
                 Message::Request {
                     body:
@@ -92,30 +119,56 @@ where
                         },
                     ..
                 } => {
-                    let ke = wire_expr.suffix;
-                    let ke = keyexpr::new(ke)?;
-                    let query = crate::api::Query::new(
-                        self,
-                        resources,
-                        id,
-                        ke,
-                        if parameters.is_empty() {
-                            None
-                        } else {
-                            Some(parameters)
-                        },
-                        match body {
-                            Some(Value { payload, .. }) => Some(payload),
-                            None => None,
-                        },
-                    );
+                    let Ok(ke) = resources.resolve(&wire_expr, &mut scratch).await else {
+                        crate::warn!("{}: Couldn't resolve the wire expression", crate::zctx!());
+                        continue;
+                    };
+                    let parameters = if parameters.is_empty() {
+                        None
+                    } else {
+                        Some(parameters)
+                    };
+                    let payload = body.map(|Value { payload, .. }| payload);
 
                     let mut queryable_cb = resources.queryable_callbacks.lock().await;
                     let count = queryable_cb.intersects(ke).count();
-                    queryable_cb.set_counter(id, count)?;
-                    for cb in queryable_cb.intersects(ke) {
+                    if count > 0 {
+                        queryable_cb.set_counter(id, count)?;
+                    }
+
+                    // A callback is done with its query once it returns, a query handed off to a
+                    // channel is finalized by its receiver
+                    let mut done = 0;
+                    for (eid, cb) in queryable_cb.intersects(ke) {
+                        let query = crate::api::Query::new(
+                            self, resources, id, eid, ke, parameters, payload,
+                        );
+
                         cb.call(&query).await;
+                        if !query.is_handed_off() {
+                            done += 1;
+                        }
+                    }
+
+                    let mut last = count == 0;
+                    for _ in 0..done {
+                        last = queryable_cb.decrease(id);
                     }
+                    drop(queryable_cb);
+
+                    if last {
+                        self.send(ResponseFinal {
+                            rid: id,
+                            ..Default::default()
+                        })
+                        .await?;
+                    }
+                }
+                Message::Declare { body, .. } => {
+                    self.declare(body, resources, &mut scratch).await?;
+                }
+                Message::Interest { body, .. } => {
+                    self.interest(body, resources, &mut scratch).await?;
                 }
                 _ => {}
             }
@@ -123,4 +176,303 @@ where
 
         Ok(())
     }
+
+    fn sample<'a>(ke: &'a keyexpr, body: PushBody<'a>) -> (Sample<'a>, Option<Timestamp>) {
+        match body {
+            PushBody::Put(Put {
+                timestamp,
+                encoding,
+                sinfo,
+                attachment,
+                payload,
+                ..
+            }) => (
+                Sample::new(ke, payload)
+                    .with_encoding(encoding)
+                    .with_timestamp(timestamp)
+                    .with_source_info(sinfo)
+                    .with_attachment(attachment.map(|a| a.buffer)),
+                timestamp,
+            ),
+            PushBody::Del(Del {
+                timestamp,
+                sinfo,
+                attachment,
+                ..
+            }) => (
+                Sample::delete(ke)
+                    .with_timestamp(timestamp)
+                    .with_source_info(sinfo)
+                    .with_attachment(attachment.map(|a| a.buffer)),
+                timestamp,
+            ),
+        }
+    }
+
+    async fn declare(
+        &self,
+        declare: Declare<'_>,
+        resources: &SessionResources<'res, Config>,
+        scratch: &mut [u8],
+    ) -> crate::ZResult<()> {
+        let (kind, id, wire_expr) = match declare.body {
+            DeclareBody::DeclareKeyExpr(DeclareKeyExpr { id, wire_expr }) => {
+                let Ok(ke) = resources.resolve(&wire_expr, scratch).await else {
+                    crate::warn!("{}: Couldn't resolve the wire expression", crate::zctx!());
+                    return Ok(());
+                };
+
+                let mut keyexprs = resources.remote_keyexprs.lock().await;
+                let _ = keyexprs.remove(id);
+                if let Err(e) = keyexprs.insert(id, ke) {
+                    crate::warn!(
+                        "{}: Couldn't store the remote key expression {}: {}",
+                        crate::zctx!(),
+                        id,
+                        e
+                    );
+                }
+
+                return Ok(());
+            }
+            DeclareBody::UndeclareKeyExpr(UndeclareKeyExpr { id }) => {
+                let _ = resources.remote_keyexprs.lock().await.remove(id);
+                return Ok(());
+            }
+            DeclareBody::DeclareFinal(_) => {
+                if let Some(id) = declare.id {
+                    let mut interests = resources.interests.lock().await;
+                    if interests.finalize(id) && interests.mode(id) == Some(InterestMode::Current) {
+                        interests.remove(id);
+                    }
+                    drop(interests);
+
+                    if resources.liveliness_get_pending(id).await {
+                        resources
+                            .complete_get(id, true, CompletionReason::Final)
+                            .await;
+                    }
+                }
+
+                return Ok(());
+            }
+            DeclareBody::DeclareSubscriber(DeclareSubscriber { id, wire_expr }) => {
+                (EntityKind::Subscriber, id, wire_expr)
+            }
+            DeclareBody::DeclareQueryable(DeclareQueryable { id, wire_expr, .. }) => {
+                (EntityKind::Queryable, id, wire_expr)
+            }
+            DeclareBody::DeclareToken(DeclareToken { id, wire_expr }) => {
+                (EntityKind::Token, id, wire_expr)
+            }
+            DeclareBody::UndeclareSubscriber(UndeclareSubscriber { id, .. }) => {
+                return Self::undeclare(EntityKind::Subscriber, id, resources, scratch).await;
+            }
+            DeclareBody::UndeclareQueryable(UndeclareQueryable { id, .. }) => {
+                return Self::undeclare(EntityKind::Queryable, id, resources, scratch).await;
+            }
+            DeclareBody::UndeclareToken(UndeclareToken { id, .. }) => {
+                return Self::undeclare(EntityKind::Token, id, resources, scratch).await;
+            }
+        };
+
+        let Ok(ke) = resources.resolve(&wire_expr, scratch).await else {
+            crate::warn!("{}: Couldn't resolve the wire expression", crate::zctx!());
+            return Ok(());
+        };
+
+        let mut interests = resources.interests.lock().await;
+
+        // Replies to a current interest are a snapshot that will never be undeclared
+        let snapshot = declare
+            .id
+            .is_some_and(|id| interests.mode(id) == Some(InterestMode::Current));
+
+        let new = snapshot
+            || interests.declare(kind, id, ke).unwrap_or_else(|_| {
+                crate::warn!("{}: Couldn't store a remote declaration", crate::zctx!());
+                true
+            });
+
+        Self::notify_matching(&mut *interests, resources).await;
+        drop(interests);
+
+        if kind != EntityKind::Token || !new {
+            return Ok(());
+        }
+
+        let sample = Sample::new(ke, &[]);
+        if let Some(id) = declare.id.filter(|_| snapshot) {
+            let mut get_cb = resources.liveliness_get_callbacks.lock().await;
+            if let Some(cb) = get_cb.get(id) {
+                cb.call(&crate::Response::Ok(sample)).await;
+            }
+        } else {
+            let mut liveliness_cb = resources.liveliness_callbacks.lock().await;
+            for (_, cb) in liveliness_cb.intersects(ke) {
+                cb.call(&sample).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn undeclare(
+        kind: EntityKind,
+        id: u32,
+        resources: &SessionResources<'res, Config>,
+        scratch: &mut [u8],
+    ) -> crate::ZResult<()> {
+        let mut interests = resources.interests.lock().await;
+
+        let len = interests.declared(kind, id).and_then(|ke| {
+            let ke = ke.as_bytes();
+            scratch.get_mut(..ke.len())?.copy_from_slice(ke);
+            Some(ke.len())
+        });
+
+        if !interests.undeclare(kind, id) {
+            return Ok(());
+        }
+
+        Self::notify_matching(&mut *interests, resources).await;
+        drop(interests);
+
+        if let (EntityKind::Token, Some(len)) = (kind, len) {
+            let ke = core::str::from_utf8(&scratch[..len])
+                .map_err(|_| crate::CodecError::CouldNotParseField)?;
+            let sample = Sample::delete(keyexpr::from_str_unchecked(ke));
+
+            let mut liveliness_cb = resources.liveliness_callbacks.lock().await;
+            for (_, cb) in liveliness_cb.intersects(sample.keyexpr()) {
+                cb.call(&sample).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn notify_matching(
+        interests: &mut Config::Interests,
+        resources: &SessionResources<'res, Config>,
+    ) {
+        let mut matching_cb = resources.matching_callbacks.lock().await;
+        while let Some((interest, matching)) = interests.changed() {
+            let update = MatchingUpdate {
+                interest,
+                status: MatchingStatus::new(matching),
+            };
+
+            // `**` walks every listener, each one only forwards the updates of its interest
+            for (_, cb) in matching_cb.intersects(keyexpr::from_str_unchecked("**")) {
+                cb.call(&update).await;
+            }
+        }
+    }
+
+    async fn interest(
+        &self,
+        interest: Interest<'_>,
+        resources: &SessionResources<'res, Config>,
+        scratch: &mut [u8],
+    ) -> crate::ZResult<()> {
+        let Interest {
+            id, mode, inner, ..
+        } = interest;
+
+        // Our declarations are always sent to the router, only the current ones are answered
+        if !matches!(mode, InterestMode::Current | InterestMode::CurrentFuture) {
+            return Ok(());
+        }
+
+        let restriction = match &inner.wire_expr {
+            Some(wire_expr) => {
+                let Ok(ke) = resources.resolve(wire_expr, scratch).await else {
+                    crate::warn!("{}: Couldn't resolve the wire expression", crate::zctx!());
+                    return Ok(());
+                };
+
+                Some(ke)
+            }
+            None => None,
+        };
+
+        let matches = |ke: &keyexpr| restriction.is_none_or(|r| r.intersects(ke));
+        let options = InterestOptions {
+            options: inner.options,
+        };
+
+        if options.keyexprs() {
+            let keyexprs = resources.keyexprs.lock().await;
+            for (kid, ke) in keyexprs.iter().filter(|(_, ke)| matches(ke)) {
+                let msg = Declare {
+                    id: Some(id),
+                    body: DeclareBody::DeclareKeyExpr(DeclareKeyExpr {
+                        id: kid,
+                        wire_expr: WireExpr::from(ke),
+                    }),
+                    ..Default::default()
+                };
+
+                self.send(msg).await?;
+            }
+        }
+
+        if options.subscribers() {
+            let subs = resources.sub_callbacks.lock().await;
+            for (sid, ke) in subs.keyexprs().filter(|(_, ke)| matches(ke)) {
+                let msg = Declare {
+                    id: Some(id),
+                    body: DeclareBody::DeclareSubscriber(DeclareSubscriber {
+                        id: sid,
+                        wire_expr: WireExpr::from(ke),
+                    }),
+                    ..Default::default()
+                };
+
+                self.send(msg).await?;
+            }
+        }
+
+        if options.queryables() {
+            let queryables = resources.queryable_callbacks.lock().await;
+            for (qid, ke) in queryables.keyexprs().filter(|(_, ke)| matches(ke)) {
+                let msg = Declare {
+                    id: Some(id),
+                    body: DeclareBody::DeclareQueryable(DeclareQueryable {
+                        id: qid,
+                        wire_expr: WireExpr::from(ke),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                };
+
+                self.send(msg).await?;
+            }
+        }
+
+        if options.tokens() {
+            let interests = resources.interests.lock().await;
+            for (tid, ke) in interests.tokens().filter(|(_, ke)| matches(ke)) {
+                let msg = Declare {
+                    id: Some(id),
+                    body: DeclareBody::DeclareToken(DeclareToken {
+                        id: tid,
+                        wire_expr: WireExpr::from(ke),
+                    }),
+                    ..Default::default()
+                };
+
+                self.send(msg).await?;
+            }
+        }
+
+        let msg = Declare {
+            id: Some(id),
+            body: DeclareBody::DeclareFinal(DeclareFinal {}),
+            ..Default::default()
+        };
+
+        self.send(msg).await
+    }
 }
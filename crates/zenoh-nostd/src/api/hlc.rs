@@ -0,0 +1,64 @@
+use core::{cell::Cell, time::Duration};
+
+use zenoh_proto::fields::{NTP64, Timestamp, ZenohIdProto};
+
+use crate::platform::ZPlatform;
+
+/// Bits of the NTP64 fraction used as a logical counter, see `uhlc`.
+const CMASK: u64 = 0xF;
+
+/// Incoming timestamps further than this in the future are not trusted.
+const MAX_DELTA: Duration = Duration::from_millis(500);
+
+/// Hybrid Logical Clock following `uhlc::HLC`, driven by the `ZPlatform` time source.
+pub(crate) struct Hlc<'res, Platform> {
+    platform: &'res Platform,
+    zid: ZenohIdProto,
+    last: Cell<NTP64>,
+}
+
+impl<'res, Platform> Hlc<'res, Platform>
+where
+    Platform: ZPlatform,
+{
+    pub(crate) fn new(platform: &'res Platform, zid: ZenohIdProto) -> Self {
+        Self {
+            platform,
+            zid,
+            last: Cell::new(NTP64(0)),
+        }
+    }
+
+    pub(crate) fn now(&self) -> NTP64 {
+        self.platform.now()
+    }
+
+    pub(crate) fn new_timestamp(&self) -> Timestamp {
+        let now = NTP64(self.platform.now().as_u64() & !CMASK);
+        let last = self.last.get();
+
+        let time = if now > last { now } else { last + 1 };
+        self.last.set(time);
+
+        self.zid.timestamp(time)
+    }
+
+    /// Moves the clock past `timestamp`, unless it drifts too far ahead of the physical time.
+    pub(crate) fn update(&self, timestamp: &Timestamp) {
+        let now = NTP64(self.platform.now().as_u64() & !CMASK);
+        let time = *timestamp.get_time();
+
+        if time > now && time - now > NTP64::from(MAX_DELTA) {
+            crate::warn!(
+                "{}: Incoming timestamp {} exceeds the maximum delta with the local clock",
+                crate::zctx!(),
+                time.as_u64()
+            );
+
+            return;
+        }
+
+        let last = self.last.get();
+        self.last.set(now.max(time + 1).max(last + 1));
+    }
+}
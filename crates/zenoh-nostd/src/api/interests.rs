@@ -0,0 +1,283 @@
+use heapless::{Deque, FnvIndexMap, String};
+use zenoh_proto::{keyexpr, msgs::InterestMode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EntityKind {
+    Subscriber,
+    Queryable,
+    Token,
+}
+
+pub trait ZInterests {
+    fn empty() -> Self;
+
+    fn insert(
+        &mut self,
+        id: u32,
+        mode: InterestMode,
+        kind: EntityKind,
+        ke: &keyexpr,
+    ) -> core::result::Result<(), crate::CollectionError>;
+
+    fn mode(&self, id: u32) -> Option<InterestMode>;
+
+    fn finalize(&mut self, id: u32) -> bool;
+    fn is_final(&self, id: u32) -> bool;
+
+    fn remove(&mut self, id: u32) -> Option<InterestMode>;
+
+    fn declare(
+        &mut self,
+        kind: EntityKind,
+        id: u32,
+        ke: &keyexpr,
+    ) -> core::result::Result<bool, crate::CollectionError>;
+
+    fn declared(&self, kind: EntityKind, id: u32) -> Option<&keyexpr>;
+    fn undeclare(&mut self, kind: EntityKind, id: u32) -> bool;
+
+    fn declare_token(
+        &mut self,
+        id: u32,
+        ke: &keyexpr,
+    ) -> core::result::Result<(), crate::CollectionError>;
+
+    fn undeclare_token(&mut self, id: u32) -> bool;
+    fn tokens(&self) -> impl Iterator<Item = (u32, &keyexpr)>;
+
+    fn matching(&self, id: u32) -> Option<bool>;
+    fn changed(&mut self) -> Option<(u32, bool)>;
+}
+
+struct InterestState<const MAX_KEYEXPR: usize> {
+    mode: InterestMode,
+    is_final: bool,
+    kind: EntityKind,
+    ke: String<MAX_KEYEXPR>,
+    matching: usize,
+    /// A matching declaration couldn't be stored, so `matching` may miss it for good.
+    overflowed: bool,
+}
+
+pub struct FixedCapacityInterests<const CAPACITY: usize, const MAX_KEYEXPR: usize = 64> {
+    interests: FnvIndexMap<u32, InterestState<MAX_KEYEXPR>, CAPACITY>,
+    declarations: FnvIndexMap<(EntityKind, u32), String<MAX_KEYEXPR>, CAPACITY>,
+    tokens: FnvIndexMap<u32, String<MAX_KEYEXPR>, CAPACITY>,
+    changed: Deque<(u32, bool), CAPACITY>,
+}
+
+impl<const CAPACITY: usize, const MAX_KEYEXPR: usize>
+    FixedCapacityInterests<CAPACITY, MAX_KEYEXPR>
+{
+    /// The interests matching a declaration that couldn't be stored report matching for the rest
+    /// of their life, so that a write filter never drops puts that have a subscriber.
+    fn overflow(&mut self, kind: EntityKind, ke: &keyexpr) {
+        for (iid, state) in self.interests.iter_mut() {
+            if state.kind == kind
+                && !state.overflowed
+                && keyexpr::from_str_unchecked(state.ke.as_str()).intersects(ke)
+            {
+                state.overflowed = true;
+
+                if state.matching == 0 && self.changed.push_back((*iid, true)).is_err() {
+                    crate::warn!("{}: Dropping a matching status change", crate::zctx!());
+                }
+            }
+        }
+    }
+}
+
+impl<const CAPACITY: usize, const MAX_KEYEXPR: usize> ZInterests
+    for FixedCapacityInterests<CAPACITY, MAX_KEYEXPR>
+{
+    fn empty() -> Self {
+        Self {
+            interests: FnvIndexMap::new(),
+            declarations: FnvIndexMap::new(),
+            tokens: FnvIndexMap::new(),
+            changed: Deque::new(),
+        }
+    }
+
+    fn insert(
+        &mut self,
+        id: u32,
+        mode: InterestMode,
+        kind: EntityKind,
+        ke: &keyexpr,
+    ) -> core::result::Result<(), crate::CollectionError> {
+        if self.interests.contains_key(&id) {
+            return Err(crate::CollectionError::KeyAlreadyExists);
+        }
+
+        let mut value = String::new();
+        value
+            .push_str(ke.as_str())
+            .map_err(|_| crate::CollectionError::CollectionTooSmall)?;
+
+        let matching = self
+            .declarations
+            .iter()
+            .filter(|((k, _), declared)| {
+                *k == kind && keyexpr::from_str_unchecked(declared.as_str()).intersects(ke)
+            })
+            .count();
+
+        let state = InterestState {
+            mode,
+            // Future interests never receive a `DeclareFinal`
+            is_final: mode == InterestMode::Future,
+            kind,
+            ke: value,
+            matching,
+            overflowed: false,
+        };
+
+        self.interests
+            .insert(id, state)
+            .map_err(|_| crate::CollectionError::CollectionIsFull)
+            .map(|_| ())
+    }
+
+    fn mode(&self, id: u32) -> Option<InterestMode> {
+        self.interests.get(&id).map(|state| state.mode)
+    }
+
+    fn finalize(&mut self, id: u32) -> bool {
+        if let Some(state) = self.interests.get_mut(&id) {
+            state.is_final = true;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn is_final(&self, id: u32) -> bool {
+        self.interests.get(&id).is_some_and(|state| state.is_final)
+    }
+
+    fn remove(&mut self, id: u32) -> Option<InterestMode> {
+        let state = self.interests.remove(&id)?;
+
+        // The router only undeclares what a future interest still covers, drop the rest
+        let interests = &self.interests;
+        self.declarations.retain(|(kind, _), declared| {
+            let declared = keyexpr::from_str_unchecked(declared.as_str());
+            interests.values().any(|state| {
+                state.kind == *kind
+                    && state.mode != InterestMode::Current
+                    && keyexpr::from_str_unchecked(state.ke.as_str()).intersects(declared)
+            })
+        });
+
+        Some(state.mode)
+    }
+
+    fn declare(
+        &mut self,
+        kind: EntityKind,
+        id: u32,
+        ke: &keyexpr,
+    ) -> core::result::Result<bool, crate::CollectionError> {
+        if self.declarations.contains_key(&(kind, id)) {
+            return Ok(false);
+        }
+
+        let mut value = String::new();
+        if value.push_str(ke.as_str()).is_err() {
+            self.overflow(kind, ke);
+            return Err(crate::CollectionError::CollectionTooSmall);
+        }
+
+        if self.declarations.insert((kind, id), value).is_err() {
+            self.overflow(kind, ke);
+            return Err(crate::CollectionError::CollectionIsFull);
+        }
+
+        for (iid, state) in self.interests.iter_mut() {
+            if state.kind == kind && keyexpr::from_str_unchecked(state.ke.as_str()).intersects(ke) {
+                state.matching += 1;
+
+                if state.matching == 1
+                    && !state.overflowed
+                    && self.changed.push_back((*iid, true)).is_err()
+                {
+                    crate::warn!("{}: Dropping a matching status change", crate::zctx!());
+                }
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn declared(&self, kind: EntityKind, id: u32) -> Option<&keyexpr> {
+        self.declarations
+            .get(&(kind, id))
+            .map(|ke| keyexpr::from_str_unchecked(ke.as_str()))
+    }
+
+    fn undeclare(&mut self, kind: EntityKind, id: u32) -> bool {
+        let Some(declared) = self.declarations.remove(&(kind, id)) else {
+            return false;
+        };
+
+        let ke = keyexpr::from_str_unchecked(declared.as_str());
+        for (iid, state) in self.interests.iter_mut() {
+            if state.kind == kind
+                && state.matching > 0
+                && keyexpr::from_str_unchecked(state.ke.as_str()).intersects(ke)
+            {
+                state.matching -= 1;
+
+                if state.matching == 0
+                    && !state.overflowed
+                    && self.changed.push_back((*iid, false)).is_err()
+                {
+                    crate::warn!("{}: Dropping a matching status change", crate::zctx!());
+                }
+            }
+        }
+
+        true
+    }
+
+    fn declare_token(
+        &mut self,
+        id: u32,
+        ke: &keyexpr,
+    ) -> core::result::Result<(), crate::CollectionError> {
+        if self.tokens.contains_key(&id) {
+            return Err(crate::CollectionError::KeyAlreadyExists);
+        }
+
+        let mut value = String::new();
+        value
+            .push_str(ke.as_str())
+            .map_err(|_| crate::CollectionError::CollectionTooSmall)?;
+
+        self.tokens
+            .insert(id, value)
+            .map_err(|_| crate::CollectionError::CollectionIsFull)
+            .map(|_| ())
+    }
+
+    fn undeclare_token(&mut self, id: u32) -> bool {
+        self.tokens.remove(&id).is_some()
+    }
+
+    fn tokens(&self) -> impl Iterator<Item = (u32, &keyexpr)> {
+        self.tokens
+            .iter()
+            .map(|(id, ke)| (*id, keyexpr::from_str_unchecked(ke.as_str())))
+    }
+
+    fn matching(&self, id: u32) -> Option<bool> {
+        self.interests
+            .get(&id)
+            .map(|state| state.overflowed || state.matching > 0)
+    }
+
+    fn changed(&mut self) -> Option<(u32, bool)> {
+        self.changed.pop_front()
+    }
+}
@@ -0,0 +1,292 @@
+use core::{fmt, fmt::Write, ops::Deref};
+
+use heapless::String;
+use zenoh_proto::{Selector, keyexpr};
+
+/// A validated key expression stored inline in a `heapless::String` of capacity `N`.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct OwnedKeyExpr<const N: usize>(String<N>);
+
+impl<const N: usize> OwnedKeyExpr<N> {
+    pub fn new(ke: &str) -> crate::ZResult<Self> {
+        Self::format(format_args!("{ke}"))
+    }
+
+    /// Builds a key expression from `format_args!`, e.g. `OwnedKeyExpr::<64>::format(format_args!("robot/{id}/pose"))`.
+    pub fn format(args: fmt::Arguments<'_>) -> crate::ZResult<Self> {
+        let mut value = String::new();
+        if value.write_fmt(args).is_err() {
+            crate::zbail!(crate::CollectionError::CollectionTooSmall);
+        }
+
+        keyexpr::new(value.as_str())?;
+
+        Ok(Self(value))
+    }
+
+    /// Appends `/` and `other`, validating the result.
+    pub fn join(&self, other: &str) -> crate::ZResult<Self> {
+        Self::format(format_args!("{}/{}", self.as_str(), other))
+    }
+
+    /// Appends `other` without a separator, validating the result.
+    pub fn concat(&self, other: &str) -> crate::ZResult<Self> {
+        Self::format(format_args!("{}{}", self.as_str(), other))
+    }
+
+    pub fn as_keyexpr(&self) -> &keyexpr {
+        keyexpr::from_str_unchecked(self.0.as_str())
+    }
+}
+
+impl<const N: usize> Deref for OwnedKeyExpr<N> {
+    type Target = keyexpr;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_keyexpr()
+    }
+}
+
+impl<const N: usize> AsRef<keyexpr> for OwnedKeyExpr<N> {
+    fn as_ref(&self) -> &keyexpr {
+        self.as_keyexpr()
+    }
+}
+
+impl<const N: usize> TryFrom<&keyexpr> for OwnedKeyExpr<N> {
+    type Error = crate::CollectionError;
+
+    fn try_from(value: &keyexpr) -> Result<Self, Self::Error> {
+        let mut ke = String::new();
+        ke.push_str(value.as_str())
+            .map_err(|_| crate::CollectionError::CollectionTooSmall)?;
+
+        Ok(Self(ke))
+    }
+}
+
+impl<'a, const N: usize> From<&'a OwnedKeyExpr<N>> for Selector<'a> {
+    fn from(value: &'a OwnedKeyExpr<N>) -> Self {
+        Selector::from(value.as_keyexpr())
+    }
+}
+
+impl<const N: usize> fmt::Display for OwnedKeyExpr<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl<const N: usize> fmt::Debug for OwnedKeyExpr<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ke`{self}`")
+    }
+}
+
+fn placeholder(chunk: &str) -> Option<&str> {
+    chunk
+        .strip_prefix('$')
+        .filter(|name| !name.starts_with('*'))
+}
+
+const fn is_ident(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_'
+}
+
+const fn chunk_end(bytes: &[u8], i: usize) -> bool {
+    i == bytes.len() || bytes[i] == b'/'
+}
+
+/// A key expression format where whole chunks can be named `$placeholders`,
+/// e.g. `robot/$id/pose`. Use `kedefine!` to check the spec at compile time.
+///
+/// Each chunk of a spec matches a single chunk, so `**` isn't allowed and placeholder values
+/// can't contain `/`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeFormat<'a> {
+    spec: &'a str,
+}
+
+impl<'a> KeFormat<'a> {
+    /// Panics if `spec` is malformed, which fails the build when evaluated in a `const`.
+    pub const fn new(spec: &'a str) -> Self {
+        let bytes = spec.as_bytes();
+        if bytes.is_empty() || bytes[bytes.len() - 1] == b'/' {
+            panic!("empty chunk in key expression format");
+        }
+
+        let mut chunk_start = 0;
+        let mut in_placeholder = false;
+
+        let len = bytes.len();
+
+        let mut i = 0;
+        while i < len {
+            match bytes[i] {
+                b'/' if i == chunk_start => panic!("empty chunk in key expression format"),
+                b'/' => {
+                    chunk_start = i + 1;
+                    in_placeholder = false;
+                }
+                c if in_placeholder && !is_ident(c) => {
+                    panic!("placeholders must span a whole chunk")
+                }
+                b'#' | b'?' => panic!("'#' or '?' in key expression format"),
+                b'*' if i == chunk_start && chunk_end(bytes, i + 1) => {}
+                // Chunks are matched one by one, so `**` can't span several of them
+                b'*' if i + 1 < len && bytes[i + 1] == b'*' => {
+                    panic!("'**' in key expression format")
+                }
+                b'*' => panic!("'*' must span a whole chunk, use '$*' within a chunk"),
+                b'$' if i + 1 < len && bytes[i + 1] == b'*' => {
+                    if i == chunk_start && chunk_end(bytes, i + 2) {
+                        panic!("lone '$*' chunk in key expression format, use '*'");
+                    }
+
+                    i += 1;
+                }
+                b'$' if i == chunk_start && i + 1 < len && is_ident(bytes[i + 1]) => {
+                    in_placeholder = true
+                }
+                b'$' => panic!("placeholders must span a whole chunk"),
+                _ => {}
+            }
+
+            i += 1;
+        }
+
+        Self { spec }
+    }
+
+    pub const fn as_str(&self) -> &'a str {
+        self.spec
+    }
+
+    pub fn placeholders(&self) -> impl Iterator<Item = &'a str> {
+        self.spec.split('/').filter_map(placeholder)
+    }
+
+    /// Replaces every placeholder with the matching value, see `keformat!`.
+    pub fn format<const N: usize>(
+        &self,
+        values: &[(&str, &dyn fmt::Display)],
+    ) -> crate::ZResult<OwnedKeyExpr<N>> {
+        let mut ke = String::<N>::new();
+
+        for (i, chunk) in self.spec.split('/').enumerate() {
+            if i > 0 && ke.push('/').is_err() {
+                crate::zbail!(crate::CollectionError::CollectionTooSmall);
+            }
+
+            let written = match placeholder(chunk) {
+                Some(name) => {
+                    let Some((_, value)) = values.iter().find(|(key, _)| *key == name) else {
+                        crate::zbail!(crate::KeyexprError::UnboundDollar);
+                    };
+
+                    let start = ke.len();
+                    let written = write!(ke, "{value}").is_ok();
+                    if written && ke[start..].contains('/') {
+                        crate::zbail!(crate::KeyexprError::SlashInChunk);
+                    }
+
+                    written
+                }
+                None => ke.push_str(chunk).is_ok(),
+            };
+
+            if !written {
+                crate::zbail!(crate::CollectionError::CollectionTooSmall);
+            }
+        }
+
+        keyexpr::new(ke.as_str())?;
+
+        Ok(OwnedKeyExpr(ke))
+    }
+
+    /// Matches `ke` against the format chunk by chunk. Placeholders and `*` match any single chunk.
+    pub fn parse<'k>(&self, ke: &'k keyexpr) -> Option<KeParsed<'a, 'k>> {
+        let mut spec = self.spec.split('/');
+        let mut chunks = ke.as_str().split('/');
+
+        loop {
+            match (spec.next(), chunks.next()) {
+                (None, None) => break,
+                (Some(s), Some(_)) if s == "*" || placeholder(s).is_some() => {}
+                (Some(s), Some(c)) if s == c => {}
+                _ => return None,
+            }
+        }
+
+        Some(KeParsed { format: *self, ke })
+    }
+}
+
+impl fmt::Display for KeFormat<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.spec)
+    }
+}
+
+impl fmt::Debug for KeFormat<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "kefmt`{self}`")
+    }
+}
+
+/// A key expression that matched a `KeFormat`.
+#[derive(Clone, Copy, Debug)]
+pub struct KeParsed<'a, 'k> {
+    format: KeFormat<'a>,
+    ke: &'k keyexpr,
+}
+
+impl<'a, 'k> KeParsed<'a, 'k> {
+    pub fn keyexpr(&self) -> &'k keyexpr {
+        self.ke
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, &'k str)> {
+        self.format
+            .spec
+            .split('/')
+            .zip(self.ke.as_str().split('/'))
+            .filter_map(|(spec, chunk)| placeholder(spec).map(|name| (name, chunk)))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&'k str> {
+        self.iter()
+            .find(|(placeholder, _)| *placeholder == name)
+            .map(|(_, value)| value)
+    }
+}
+
+/// Declares `KeFormat` constants whose spec is checked at compile time.
+///
+/// ```
+/// use zenoh_nostd::OwnedKeyExpr;
+///
+/// zenoh_nostd::kedefine!(robot_pose: "robot/$id/pose");
+///
+/// let ke: OwnedKeyExpr<64> = zenoh_nostd::keformat!(robot_pose, id = 42).unwrap();
+/// let id = robot_pose.parse(&ke).and_then(|parsed| parsed.get("id"));
+/// assert_eq!(id, Some("42"));
+/// ```
+#[macro_export]
+macro_rules! kedefine {
+    ($($vis:vis $name:ident: $spec:literal),* $(,)?) => {
+        $(
+            #[allow(non_upper_case_globals)]
+            $vis const $name: $crate::KeFormat<'static> = $crate::KeFormat::new($spec);
+        )*
+    };
+}
+
+/// Formats a `KeFormat` into an `OwnedKeyExpr` from `name = value` pairs.
+#[macro_export]
+macro_rules! keformat {
+    ($format:expr, $($name:ident = $value:expr),* $(,)?) => {
+        $format.format(&[$((stringify!($name), &$value as &dyn core::fmt::Display)),*])
+    };
+}
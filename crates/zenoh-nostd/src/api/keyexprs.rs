@@ -0,0 +1,117 @@
+use heapless::{FnvIndexMap, String};
+use zenoh_proto::keyexpr;
+
+pub trait ZKeyExprs {
+    fn empty() -> Self;
+
+    fn insert(&mut self, id: u16, ke: &keyexpr)
+    -> core::result::Result<(), crate::CollectionError>;
+
+    fn get(&self, id: u16) -> Option<&keyexpr>;
+    fn find(&self, ke: &keyexpr) -> Option<u16>;
+    /// The smallest id not in use, starting at 1 as the scope 0 is reserved for non-declared
+    /// key expressions.
+    fn next_id(&self) -> Option<u16>;
+    fn iter(&self) -> impl Iterator<Item = (u16, &keyexpr)>;
+
+    fn retain(&mut self, id: u16) -> bool;
+    fn release(&mut self, id: u16) -> bool;
+
+    fn remove(&mut self, id: u16) -> core::result::Result<(), crate::CollectionError>;
+}
+
+pub struct FixedCapacityKeyExprs<const CAPACITY: usize, const MAX_KEYEXPR: usize = 64> {
+    keyexprs: FnvIndexMap<u16, String<MAX_KEYEXPR>, CAPACITY>,
+    counters: FnvIndexMap<u16, usize, CAPACITY>,
+}
+
+impl<const CAPACITY: usize, const MAX_KEYEXPR: usize> ZKeyExprs
+    for FixedCapacityKeyExprs<CAPACITY, MAX_KEYEXPR>
+{
+    fn empty() -> Self {
+        Self {
+            keyexprs: FnvIndexMap::new(),
+            counters: FnvIndexMap::new(),
+        }
+    }
+
+    fn insert(
+        &mut self,
+        id: u16,
+        ke: &keyexpr,
+    ) -> core::result::Result<(), crate::CollectionError> {
+        if self.keyexprs.contains_key(&id) {
+            return Err(crate::CollectionError::KeyAlreadyExists);
+        }
+
+        let mut value = String::new();
+        value
+            .push_str(ke.as_str())
+            .map_err(|_| crate::CollectionError::CollectionTooSmall)?;
+
+        self.keyexprs
+            .insert(id, value)
+            .map_err(|_| crate::CollectionError::CollectionIsFull)?;
+
+        self.counters
+            .insert(id, 1)
+            .map_err(|_| crate::CollectionError::CollectionIsFull)
+            .map(|_| ())
+    }
+
+    fn get(&self, id: u16) -> Option<&keyexpr> {
+        self.keyexprs
+            .get(&id)
+            .map(|ke| keyexpr::from_str_unchecked(ke.as_str()))
+    }
+
+    fn find(&self, ke: &keyexpr) -> Option<u16> {
+        self.keyexprs
+            .iter()
+            .find(|(_, registered_ke)| registered_ke.as_str() == ke.as_str())
+            .map(|(id, _)| *id)
+    }
+
+    fn next_id(&self) -> Option<u16> {
+        (1..=u16::MAX).find(|id| !self.keyexprs.contains_key(id))
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (u16, &keyexpr)> {
+        self.keyexprs
+            .iter()
+            .map(|(id, ke)| (*id, keyexpr::from_str_unchecked(ke.as_str())))
+    }
+
+    fn retain(&mut self, id: u16) -> bool {
+        if let Some(value) = self.counters.get_mut(&id) {
+            *value += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn release(&mut self, id: u16) -> bool {
+        let Some(value) = self.counters.get_mut(&id) else {
+            return false;
+        };
+
+        *value = value.saturating_sub(1);
+
+        if *value == 0 {
+            self.keyexprs.remove(&id);
+            self.counters.remove(&id);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn remove(&mut self, id: u16) -> core::result::Result<(), crate::CollectionError> {
+        self.counters.remove(&id);
+        self.keyexprs
+            .remove(&id)
+            .map(|_| ())
+            .ok_or(crate::CollectionError::KeyNotFound)
+    }
+}
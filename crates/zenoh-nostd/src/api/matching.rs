@@ -0,0 +1,73 @@
+use dyn_utils::DynObject;
+use zenoh_proto::keyexpr;
+
+use crate::api::{
+    ZConfig,
+    arg::MatchingUpdate,
+    callbacks::{AsyncCallback, ZCallbacks},
+    resources::SessionResources,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchingStatus {
+    matching: bool,
+}
+
+impl MatchingStatus {
+    pub(crate) fn new(matching: bool) -> Self {
+        Self { matching }
+    }
+
+    pub fn matching(&self) -> bool {
+        self.matching
+    }
+}
+
+/// A listener declared by `Publisher::matching_listener` or `Querier::matching_listener`. It
+/// stays declared until undeclared, but isn't notified anymore once its entity is undeclared.
+pub struct MatchingListener<'a, 'res, Config>
+where
+    Config: ZConfig,
+{
+    resources: &'a SessionResources<'res, Config>,
+
+    id: u32,
+}
+
+impl<'a, 'res, Config> MatchingListener<'a, 'res, Config>
+where
+    Config: ZConfig,
+{
+    pub(crate) async fn new(
+        resources: &'a SessionResources<'res, Config>,
+        interest: u32,
+        ke: &keyexpr,
+        mut callback: impl AsyncFnMut(&MatchingStatus) + 'res,
+    ) -> crate::ZResult<Self> {
+        let id = resources.next().await;
+
+        let mut matching_cb = resources.matching_callbacks.lock().await;
+        matching_cb.insert(
+            id,
+            ke,
+            None,
+            DynObject::new(AsyncCallback::new(async move |update: &MatchingUpdate| {
+                if update.interest == interest {
+                    callback(&update.status).await;
+                }
+            })),
+        )?;
+
+        Ok(Self { resources, id })
+    }
+
+    pub async fn undeclare(self) -> crate::ZResult<()> {
+        self.resources
+            .matching_callbacks
+            .lock()
+            .await
+            .remove(self.id)?;
+
+        Ok(())
+    }
+}
@@ -1,9 +1,11 @@
-use core::str::FromStr;
+use core::{cell::Cell, str::FromStr};
 
 use zenoh_proto::{
-    fields::{ConsolidationMode, Encoding, WireExpr},
+    Parameters, Selector,
+    exts::{Attachment, EntityGlobalId, SourceInfo},
+    fields::{ConsolidationMode, Encoding, NTP64, Timestamp, WireExpr},
     keyexpr,
-    msgs::{Err, PushBody, Put, Reply, Response, ResponseBody, ResponseFinal},
+    msgs::{Del, Err, PushBody, Put, Reply, Response, ResponseBody, ResponseFinal},
     zerror::CollectionError,
 };
 
@@ -19,9 +21,11 @@ where
     driver: &'a Driver<'res, Config>,
     resources: &'a SessionResources<'res, Config>,
     rid: u32,
+    eid: u32,
     ke: &'a keyexpr,
     parameters: Option<&'a str>,
     payload: Option<&'a [u8]>,
+    handed_off: Cell<bool>,
 }
 
 impl<'a, 'res, Config> Query<'a, 'res, Config>
@@ -32,6 +36,7 @@ where
         driver: &'a Driver<'res, Config>,
         resources: &'a SessionResources<'res, Config>,
         rid: u32,
+        eid: u32,
         ke: &'a keyexpr,
         parameters: Option<&'a str>,
         payload: Option<&'a [u8]>,
@@ -40,119 +45,280 @@ where
             driver,
             resources,
             rid,
+            eid,
             ke,
             parameters,
             payload,
+            handed_off: Cell::new(false),
         }
     }
 
+    /// Marks the query as moved to a channel, its receiver finalizes it instead of the driver.
+    pub(crate) fn hand_off(&self) {
+        self.handed_off.set(true);
+    }
+
+    pub(crate) fn is_handed_off(&self) -> bool {
+        self.handed_off.get()
+    }
+
     pub fn keyexpr(&self) -> &keyexpr {
         self.ke
     }
 
-    pub fn parameters(&self) -> Option<&str> {
-        self.parameters
+    pub fn parameters(&self) -> Parameters<'_> {
+        Parameters::new(self.parameters.unwrap_or_default())
+    }
+
+    pub fn selector(&self) -> Selector<'_> {
+        Selector::new(self.ke, self.parameters.unwrap_or_default())
     }
 
     pub fn payload(&self) -> Option<&[u8]> {
         self.payload
     }
 
-    pub async fn reply(&self, ke: &keyexpr, payload: &[u8]) -> crate::ZResult<()> {
-        let wke = WireExpr::from(ke);
+    pub(crate) fn now(&self) -> Option<NTP64> {
+        self.resources.now()
+    }
 
-        let response = Response {
-            rid: self.rid,
-            wire_expr: wke,
-            payload: ResponseBody::Reply(Reply {
-                consolidation: ConsolidationMode::None,
-                payload: PushBody::Put(Put {
-                    payload,
-                    ..Default::default()
-                }),
-            }),
+    pub fn reply<'b>(
+        &'b self,
+        ke: &'b keyexpr,
+        payload: &'b [u8],
+    ) -> ReplyBuilder<'b, 'res, Config> {
+        ReplyBuilder::new(
+            self.driver,
+            self.rid,
+            self.resources.entity_global_id(self.eid),
+            self.ke,
+            self.parameters(),
+            ke,
+            payload,
+        )
+    }
+
+    pub fn reply_del<'b>(&'b self, ke: &'b keyexpr) -> ReplyDelBuilder<'b, 'res, Config> {
+        ReplyDelBuilder::new(
+            self.driver,
+            self.rid,
+            self.resources.entity_global_id(self.eid),
+            self.ke,
+            self.parameters(),
+            ke,
+        )
+    }
+
+    pub fn err<'b>(
+        &'b self,
+        ke: &'b keyexpr,
+        payload: &'b [u8],
+    ) -> ReplyErrBuilder<'b, 'res, Config> {
+        ReplyErrBuilder::new(
+            self.driver,
+            self.rid,
+            self.resources.entity_global_id(self.eid),
+            ke,
+            payload,
+        )
+    }
+}
+
+async fn finalize<Config>(
+    driver: &Driver<'_, Config>,
+    resources: &SessionResources<'_, Config>,
+    rid: u32,
+) -> crate::ZResult<()>
+where
+    Config: ZConfig,
+{
+    let mut queryable_cb = resources.queryable_callbacks.lock().await;
+    if queryable_cb.decrease(rid) {
+        let response = ResponseFinal {
+            rid,
             ..Default::default()
         };
 
-        self.driver.send(response).await
+        driver.send(response).await?;
+    }
+
+    Ok(())
+}
+
+/// Whether the query parameters contain `_anyke`, allowing replies on any key expression.
+fn accepts_any_keyexpr(parameters: Parameters<'_>) -> bool {
+    parameters.contains_key("_anyke")
+}
+
+fn check_keyexpr(query: &keyexpr, parameters: Parameters<'_>, ke: &keyexpr) -> crate::ZResult<()> {
+    if !accepts_any_keyexpr(parameters) && !query.intersects(ke) {
+        crate::zbail!(crate::SessionError::KeyexprMismatch);
     }
 
-    pub async fn err(&self, ke: &keyexpr, payload: &[u8]) -> crate::ZResult<()> {
-        let wke = WireExpr::from(ke);
+    Ok(())
+}
+
+pub struct ReplyBuilder<'a, 'res, Config>
+where
+    Config: ZConfig,
+{
+    driver: &'a Driver<'res, Config>,
+    rid: u32,
+    respid: EntityGlobalId,
+    query: &'a keyexpr,
+    parameters: Parameters<'a>,
+
+    ke: &'a keyexpr,
+    payload: &'a [u8],
+
+    encoding: Encoding<'a>,
+    timestamp: Option<Timestamp>,
+    sinfo: Option<SourceInfo>,
+    attachment: Option<Attachment<'a>>,
+}
+
+impl<'a, 'res, Config> ReplyBuilder<'a, 'res, Config>
+where
+    Config: ZConfig,
+{
+    fn new(
+        driver: &'a Driver<'res, Config>,
+        rid: u32,
+        respid: EntityGlobalId,
+        query: &'a keyexpr,
+        parameters: Parameters<'a>,
+        ke: &'a keyexpr,
+        payload: &'a [u8],
+    ) -> Self {
+        Self {
+            driver,
+            rid,
+            respid,
+            query,
+            parameters,
+            ke,
+            payload,
+            encoding: Encoding::default(),
+            timestamp: None,
+            sinfo: None,
+            attachment: None,
+        }
+    }
+
+    pub fn encoding(mut self, encoding: Encoding<'a>) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: Timestamp) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn source_info(mut self, sinfo: SourceInfo) -> Self {
+        self.sinfo = Some(sinfo);
+        self
+    }
+
+    pub fn attachment(mut self, attachment: &'a [u8]) -> Self {
+        self.attachment = Some(Attachment { buffer: attachment });
+        self
+    }
+
+    pub async fn finish(self) -> crate::ZResult<()> {
+        check_keyexpr(self.query, self.parameters, self.ke)?;
 
         let response = Response {
             rid: self.rid,
-            wire_expr: wke,
-            payload: ResponseBody::Err(Err {
-                encoding: Encoding::default(),
-                payload,
-                ..Default::default()
+            wire_expr: WireExpr::from(self.ke),
+            respid: Some(self.respid),
+            payload: ResponseBody::Reply(Reply {
+                consolidation: ConsolidationMode::None,
+                payload: PushBody::Put(Put {
+                    payload: self.payload,
+                    encoding: self.encoding,
+                    timestamp: self.timestamp,
+                    sinfo: self.sinfo,
+                    attachment: self.attachment,
+                }),
             }),
             ..Default::default()
         };
 
         self.driver.send(response).await
     }
-
-    pub async fn finalize(&self) -> crate::ZResult<()> {
-        let mut queryable_cb = self.resources.queryable_callbacks.lock().await;
-        if queryable_cb.decrease(self.rid) {
-            let response = ResponseFinal {
-                rid: self.rid,
-                ..Default::default()
-            };
-
-            self.driver.send(response).await?;
-        }
-
-        Ok(())
-    }
 }
 
-pub struct OwnedQuery<
-    Config,
-    const MAX_KEYEXPR: usize,
-    const MAX_PARAMETERS: usize,
-    const MAX_PAYLOAD: usize,
-> where
+pub struct ReplyDelBuilder<'a, 'res, Config>
+where
     Config: ZConfig,
 {
-    driver: &'static Driver<'static, Config>,
-    resources: &'static SessionResources<'static, Config>,
+    driver: &'a Driver<'res, Config>,
     rid: u32,
-    ke: heapless::String<MAX_KEYEXPR>,
-    parameters: Option<heapless::String<MAX_PARAMETERS>>,
-    payload: Option<heapless::Vec<u8, MAX_PAYLOAD>>,
+    respid: EntityGlobalId,
+    query: &'a keyexpr,
+    parameters: Parameters<'a>,
+
+    ke: &'a keyexpr,
+
+    timestamp: Option<Timestamp>,
+    sinfo: Option<SourceInfo>,
+    attachment: Option<Attachment<'a>>,
 }
 
-impl<Config, const MAX_KEYEXPR: usize, const MAX_PARAMETERS: usize, const MAX_PAYLOAD: usize>
-    OwnedQuery<Config, MAX_KEYEXPR, MAX_PARAMETERS, MAX_PAYLOAD>
+impl<'a, 'res, Config> ReplyDelBuilder<'a, 'res, Config>
 where
     Config: ZConfig,
 {
-    pub fn keyexpr(&self) -> &keyexpr {
-        keyexpr::from_str_unchecked(self.ke.as_str())
+    fn new(
+        driver: &'a Driver<'res, Config>,
+        rid: u32,
+        respid: EntityGlobalId,
+        query: &'a keyexpr,
+        parameters: Parameters<'a>,
+        ke: &'a keyexpr,
+    ) -> Self {
+        Self {
+            driver,
+            rid,
+            respid,
+            query,
+            parameters,
+            ke,
+            timestamp: None,
+            sinfo: None,
+            attachment: None,
+        }
     }
 
-    pub fn parameters(&self) -> Option<&str> {
-        self.parameters.as_ref().map(|p| p.as_str())
+    pub fn timestamp(mut self, timestamp: Timestamp) -> Self {
+        self.timestamp = Some(timestamp);
+        self
     }
 
-    pub fn payload(&self) -> Option<&[u8]> {
-        self.payload.as_ref().map(|p| p.as_slice())
+    pub fn source_info(mut self, sinfo: SourceInfo) -> Self {
+        self.sinfo = Some(sinfo);
+        self
     }
 
-    pub async fn reply(&self, ke: &keyexpr, payload: &[u8]) -> crate::ZResult<()> {
-        let wke = WireExpr::from(ke);
+    pub fn attachment(mut self, attachment: &'a [u8]) -> Self {
+        self.attachment = Some(Attachment { buffer: attachment });
+        self
+    }
+
+    pub async fn finish(self) -> crate::ZResult<()> {
+        check_keyexpr(self.query, self.parameters, self.ke)?;
 
         let response = Response {
             rid: self.rid,
-            wire_expr: wke,
+            wire_expr: WireExpr::from(self.ke),
+            respid: Some(self.respid),
             payload: ResponseBody::Reply(Reply {
                 consolidation: ConsolidationMode::None,
-                payload: PushBody::Put(Put {
-                    payload,
-                    ..Default::default()
+                payload: PushBody::Del(Del {
+                    timestamp: self.timestamp,
+                    sinfo: self.sinfo,
+                    attachment: self.attachment,
                 }),
             }),
             ..Default::default()
@@ -160,16 +326,56 @@ where
 
         self.driver.send(response).await
     }
+}
+
+pub struct ReplyErrBuilder<'a, 'res, Config>
+where
+    Config: ZConfig,
+{
+    driver: &'a Driver<'res, Config>,
+    rid: u32,
+    respid: EntityGlobalId,
+
+    ke: &'a keyexpr,
+    payload: &'a [u8],
 
-    pub async fn err(&self, ke: &keyexpr, payload: &[u8]) -> crate::ZResult<()> {
-        let wke = WireExpr::from(ke);
+    encoding: Encoding<'a>,
+}
 
+impl<'a, 'res, Config> ReplyErrBuilder<'a, 'res, Config>
+where
+    Config: ZConfig,
+{
+    fn new(
+        driver: &'a Driver<'res, Config>,
+        rid: u32,
+        respid: EntityGlobalId,
+        ke: &'a keyexpr,
+        payload: &'a [u8],
+    ) -> Self {
+        Self {
+            driver,
+            rid,
+            respid,
+            ke,
+            payload,
+            encoding: Encoding::default(),
+        }
+    }
+
+    pub fn encoding(mut self, encoding: Encoding<'a>) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    pub async fn finish(self) -> crate::ZResult<()> {
         let response = Response {
             rid: self.rid,
-            wire_expr: wke,
+            wire_expr: WireExpr::from(self.ke),
+            respid: Some(self.respid),
             payload: ResponseBody::Err(Err {
-                encoding: Encoding::default(),
-                payload,
+                encoding: self.encoding,
+                payload: self.payload,
                 ..Default::default()
             }),
             ..Default::default()
@@ -177,46 +383,57 @@ where
 
         self.driver.send(response).await
     }
+}
 
-    pub async fn finalize(&self) -> crate::ZResult<()> {
-        let mut queryable_cb = self.resources.queryable_callbacks.lock().await;
-        if queryable_cb.decrease(self.rid) {
-            let response = ResponseFinal {
-                rid: self.rid,
-                ..Default::default()
-            };
+#[derive(Debug)]
+pub struct OwnedQuery<
+    const MAX_KEYEXPR: usize,
+    const MAX_PARAMETERS: usize,
+    const MAX_PAYLOAD: usize,
+> {
+    rid: u32,
+    eid: u32,
+    ke: heapless::String<MAX_KEYEXPR>,
+    parameters: Option<heapless::String<MAX_PARAMETERS>>,
+    payload: Option<heapless::Vec<u8, MAX_PAYLOAD>>,
+}
 
-            self.driver.send(response).await?;
-        }
+impl<const MAX_KEYEXPR: usize, const MAX_PARAMETERS: usize, const MAX_PAYLOAD: usize>
+    OwnedQuery<MAX_KEYEXPR, MAX_PARAMETERS, MAX_PAYLOAD>
+{
+    pub fn keyexpr(&self) -> &keyexpr {
+        keyexpr::from_str_unchecked(self.ke.as_str())
+    }
 
-        Ok(())
+    pub fn parameters(&self) -> Parameters<'_> {
+        Parameters::new(
+            self.parameters
+                .as_ref()
+                .map(|p| p.as_str())
+                .unwrap_or_default(),
+        )
+    }
+
+    pub fn selector(&self) -> Selector<'_> {
+        Selector::new(self.keyexpr(), self.parameters().as_str())
+    }
+
+    pub fn payload(&self) -> Option<&[u8]> {
+        self.payload.as_ref().map(|p| p.as_slice())
     }
 }
 
-impl<'a, Config, const MAX_KEYEXPR: usize, const MAX_PARAMETERS: usize, const MAX_PAYLOAD: usize>
-    TryFrom<(
-        &Query<'a, 'static, Config>,
-        &'static Driver<'static, Config>,
-        &'static SessionResources<'static, Config>,
-    )> for OwnedQuery<Config, MAX_KEYEXPR, MAX_PARAMETERS, MAX_PAYLOAD>
+impl<Config, const MAX_KEYEXPR: usize, const MAX_PARAMETERS: usize, const MAX_PAYLOAD: usize>
+    TryFrom<&Query<'_, '_, Config>> for OwnedQuery<MAX_KEYEXPR, MAX_PARAMETERS, MAX_PAYLOAD>
 where
     Config: ZConfig,
 {
     type Error = CollectionError;
 
-    fn try_from(
-        value: (
-            &Query<'a, 'static, Config>,
-            &'static Driver<'static, Config>,
-            &'static SessionResources<'static, Config>,
-        ),
-    ) -> Result<Self, Self::Error> {
-        let (value, driver, resources) = value;
-
+    fn try_from(value: &Query<'_, '_, Config>) -> Result<Self, Self::Error> {
         Ok(Self {
-            driver,
-            resources,
             rid: value.rid,
+            eid: value.eid,
             ke: heapless::String::from_str(value.keyexpr().as_str())
                 .map_err(|_| CollectionError::CollectionTooSmall)?,
             parameters: value
@@ -232,3 +449,105 @@ where
         })
     }
 }
+
+/// A query received through a `Queryable` channel, bound back to its session to reply.
+pub struct ReceivedQuery<'a, 'res, Config, OwnedQuery>
+where
+    Config: ZConfig,
+{
+    driver: &'a Driver<'res, Config>,
+    resources: &'a SessionResources<'res, Config>,
+    query: OwnedQuery,
+}
+
+impl<'a, 'res, Config, OwnedQuery> ReceivedQuery<'a, 'res, Config, OwnedQuery>
+where
+    Config: ZConfig,
+{
+    pub(crate) fn new(
+        driver: &'a Driver<'res, Config>,
+        resources: &'a SessionResources<'res, Config>,
+        query: OwnedQuery,
+    ) -> Self {
+        Self {
+            driver,
+            resources,
+            query,
+        }
+    }
+
+    /// Unbinds the query from its session, it can't be finalized anymore.
+    pub fn into_inner(self) -> OwnedQuery {
+        self.query
+    }
+}
+
+impl<Config, OwnedQuery> core::ops::Deref for ReceivedQuery<'_, '_, Config, OwnedQuery>
+where
+    Config: ZConfig,
+{
+    type Target = OwnedQuery;
+
+    fn deref(&self) -> &Self::Target {
+        &self.query
+    }
+}
+
+impl<
+    'a,
+    'res,
+    Config,
+    const MAX_KEYEXPR: usize,
+    const MAX_PARAMETERS: usize,
+    const MAX_PAYLOAD: usize,
+> ReceivedQuery<'a, 'res, Config, OwnedQuery<MAX_KEYEXPR, MAX_PARAMETERS, MAX_PAYLOAD>>
+where
+    Config: ZConfig,
+{
+    pub fn reply<'b>(
+        &'b self,
+        ke: &'b keyexpr,
+        payload: &'b [u8],
+    ) -> ReplyBuilder<'b, 'res, Config> {
+        ReplyBuilder::new(
+            self.driver,
+            self.query.rid,
+            self.resources.entity_global_id(self.query.eid),
+            self.query.keyexpr(),
+            self.query.parameters(),
+            ke,
+            payload,
+        )
+    }
+
+    pub fn reply_del<'b>(&'b self, ke: &'b keyexpr) -> ReplyDelBuilder<'b, 'res, Config> {
+        ReplyDelBuilder::new(
+            self.driver,
+            self.query.rid,
+            self.resources.entity_global_id(self.query.eid),
+            self.query.keyexpr(),
+            self.query.parameters(),
+            ke,
+        )
+    }
+
+    pub fn err<'b>(
+        &'b self,
+        ke: &'b keyexpr,
+        payload: &'b [u8],
+    ) -> ReplyErrBuilder<'b, 'res, Config> {
+        ReplyErrBuilder::new(
+            self.driver,
+            self.query.rid,
+            self.resources.entity_global_id(self.query.eid),
+            ke,
+            payload,
+        )
+    }
+
+    /// Sends the `ResponseFinal` once every queryable is done with the query. Nothing is sent
+    /// for a query dropped without being finalized, so the querier waits until its timeout.
+    pub async fn finalize(self) -> crate::ZResult<()> {
+        finalize(self.driver, self.resources, self.query.rid).await
+    }
+}
@@ -1,10 +1,34 @@
 use crate::{
-    api::{Session, ZConfig, callbacks::*, driver::*},
+    api::{
+        CompletionReason, Session, ZConfig,
+        callbacks::*,
+        consolidation::ZConsolidation,
+        driver::*,
+        hlc::Hlc,
+        interests::{EntityKind, ZInterests},
+        keyexprs::ZKeyExprs,
+    },
     io::transport::{Transport, TransportConfig},
 };
 
-use embassy_sync::{blocking_mutex::raw::NoopRawMutex, mutex::Mutex};
+use core::{cell::RefCell, future::poll_fn, task::Poll};
+
+use embassy_sync::{
+    blocking_mutex::{self, raw::NoopRawMutex},
+    mutex::Mutex,
+    signal::Signal,
+    waitqueue::MultiWakerRegistration,
+};
 use embassy_time::Instant;
+use zenoh_proto::{
+    exts::EntityGlobalId,
+    fields::{Field, Mapping, NTP64, Resolution, Timestamp, WireExpr, ZenohIdProto},
+    keyexpr,
+    msgs::{
+        Declare, DeclareBody, DeclareKeyExpr, Interest, InterestFinal, InterestInner, InterestMode,
+        InterestOptions, UndeclareKeyExpr, UndeclareQueryable,
+    },
+};
 
 pub struct Resources<Config>
 where
@@ -12,6 +36,8 @@ where
 {
     platform: Option<Config::Platform>,
     transport: Option<Transport<Config::Platform>>,
+    #[cfg(feature = "compression")]
+    pub(crate) compression: Option<(&'static mut [u8], &'static mut [u8])>,
 }
 
 impl<Config> Default for Resources<Config>
@@ -31,6 +57,8 @@ where
         Self {
             platform: None,
             transport: None,
+            #[cfg(feature = "compression")]
+            compression: None,
         }
     }
 }
@@ -48,26 +76,45 @@ where
         let Self {
             platform: platform_ref_mut,
             transport: transport_ref_mut,
+            #[cfg(feature = "compression")]
+                compression: compression_ref_mut,
         } = self;
 
+        let timestamping = config.timestamping();
         let (platform, tx_buf, rx_buf) = config.into_parts();
 
-        *platform_ref_mut = Some(platform);
+        let platform = &*platform_ref_mut.insert(platform);
         *transport_ref_mut = Some(transport);
 
+        let hlc = timestamping.then(|| Hlc::new(platform, tconfig.mine_config.mine_zid.clone()));
+
         let (tx, rx) = {
             let (tx, rx) = transport_ref_mut.as_mut().unwrap().split();
+            let (tx, rx) = (
+                tx.with_lowlatency(tconfig.negociated_config.lowlatency),
+                rx.with_lowlatency(tconfig.negociated_config.lowlatency),
+            );
+
+            #[cfg(feature = "compression")]
+            let (tx, rx) = match compression_ref_mut.take() {
+                Some((ctx, crx)) if tconfig.negociated_config.compression => {
+                    (tx.with_compression(ctx), rx.with_compression(crx))
+                }
+                _ => (tx, rx),
+            };
             (
                 DriverTx {
                     tx_buf,
                     tx,
                     sn: tconfig.negociated_config.mine_sn,
+                    resolution: tconfig.negociated_config.resolution,
                     next_keepalive: Instant::now(),
                     config: tconfig.mine_config.clone(),
                 },
                 DriverRx {
                     rx_buf,
                     rx,
+                    resolution: tconfig.negociated_config.resolution,
                     last_read: Instant::now(),
                     config: tconfig.other_config.clone(),
                 },
@@ -76,7 +123,11 @@ where
 
         Session {
             driver: Driver::new(tx, rx),
-            resources: SessionResources::new(),
+            resources: SessionResources::new(
+                tconfig.negociated_config.resolution,
+                tconfig.mine_config.mine_zid.clone(),
+                hlc,
+            ),
         }
     }
 }
@@ -86,21 +137,59 @@ where
     Config: ZConfig,
 {
     pub next: Mutex<NoopRawMutex, u32>,
+    pub next_rid: Mutex<NoopRawMutex, u32>,
+    pub resolution: Resolution,
+    pub zid: ZenohIdProto,
+    pub hlc: Option<Hlc<'res, Config::Platform>>,
+    pub keyexprs: Mutex<NoopRawMutex, Config::KeyExprs>,
+    pub remote_keyexprs: Mutex<NoopRawMutex, Config::KeyExprs>,
+    pub interests: Mutex<NoopRawMutex, Config::Interests>,
+    pub consolidation: Mutex<NoopRawMutex, Config::Consolidation>,
     pub get_callbacks: Mutex<NoopRawMutex, Config::GetCallbacks<'res>>,
     pub sub_callbacks: Mutex<NoopRawMutex, Config::SubCallbacks<'res>>,
     pub queryable_callbacks: Mutex<NoopRawMutex, Config::QueryableCallbacks<'res>>,
+    pub matching_callbacks: Mutex<NoopRawMutex, Config::MatchingCallbacks<'res>>,
+    pub liveliness_callbacks: Mutex<NoopRawMutex, Config::SubCallbacks<'res>>,
+    pub liveliness_get_callbacks: Mutex<NoopRawMutex, Config::GetCallbacks<'res>>,
+    pub completion_callbacks: Mutex<NoopRawMutex, Config::CompletionCallbacks<'res>>,
+    pub liveliness_completion_callbacks: Mutex<NoopRawMutex, Config::CompletionCallbacks<'res>>,
+    pub get_waiters:
+        blocking_mutex::Mutex<NoopRawMutex, RefCell<MultiWakerRegistration<MAX_GET_WAITERS>>>,
+    pub get_inserted: Signal<NoopRawMutex, ()>,
 }
 
-impl<Config> SessionResources<'_, Config>
+/// Number of `Responses::recv` that can wait for a completion before they all get woken up.
+const MAX_GET_WAITERS: usize = 4;
+
+impl<'res, Config> SessionResources<'res, Config>
 where
     Config: ZConfig,
 {
-    pub fn new() -> Self {
+    pub fn new(
+        resolution: Resolution,
+        zid: ZenohIdProto,
+        hlc: Option<Hlc<'res, Config::Platform>>,
+    ) -> Self {
         Self {
             next: Mutex::new(0),
+            next_rid: Mutex::new(0),
+            resolution,
+            zid,
+            hlc,
+            keyexprs: Mutex::new(Config::KeyExprs::empty()),
+            remote_keyexprs: Mutex::new(Config::KeyExprs::empty()),
+            interests: Mutex::new(Config::Interests::empty()),
+            consolidation: Mutex::new(Config::Consolidation::empty()),
             get_callbacks: Mutex::new(Config::GetCallbacks::empty()),
             sub_callbacks: Mutex::new(Config::SubCallbacks::empty()),
             queryable_callbacks: Mutex::new(Config::QueryableCallbacks::empty()),
+            matching_callbacks: Mutex::new(Config::MatchingCallbacks::empty()),
+            liveliness_callbacks: Mutex::new(Config::SubCallbacks::empty()),
+            liveliness_get_callbacks: Mutex::new(Config::GetCallbacks::empty()),
+            completion_callbacks: Mutex::new(Config::CompletionCallbacks::empty()),
+            liveliness_completion_callbacks: Mutex::new(Config::CompletionCallbacks::empty()),
+            get_waiters: blocking_mutex::Mutex::new(RefCell::new(MultiWakerRegistration::new())),
+            get_inserted: Signal::new(),
         }
     }
 
@@ -110,4 +199,293 @@ where
         *guard += 1;
         next
     }
+
+    /// The global id of the local entity `eid`, as sent in `SourceInfo` and `respid`.
+    pub fn entity_global_id(&self, eid: u32) -> EntityGlobalId {
+        EntityGlobalId {
+            zid: self.zid.clone(),
+            eid,
+        }
+    }
+
+    pub fn new_timestamp(&self) -> Option<Timestamp> {
+        self.hlc.as_ref().map(Hlc::new_timestamp)
+    }
+
+    /// The physical time of the session clock, if timestamping is enabled.
+    pub fn now(&self) -> Option<NTP64> {
+        self.hlc.as_ref().map(Hlc::now)
+    }
+
+    pub fn update_hlc(&self, timestamp: Option<&Timestamp>) {
+        if let (Some(hlc), Some(timestamp)) = (&self.hlc, timestamp) {
+            hlc.update(timestamp);
+        }
+    }
+
+    pub async fn next_rid(&self) -> u32 {
+        let mut guard = self.next_rid.lock().await;
+        let next = *guard;
+        *guard = next.wrapping_add(1) & self.resolution.mask(Field::RequestID);
+        next
+    }
+
+    pub(crate) async fn declare_keyexpr(
+        &self,
+        driver: &Driver<'res, Config>,
+        ke: &keyexpr,
+    ) -> crate::ZResult<u16> {
+        let id = {
+            let mut keyexprs = self.keyexprs.lock().await;
+            if let Some(id) = keyexprs.find(ke) {
+                keyexprs.retain(id);
+                return Ok(id);
+            }
+
+            // Ids of undeclared key expressions are reused, so that they never alias a live one
+            let Some(id) = keyexprs.next_id() else {
+                crate::zbail!(crate::CollectionError::CollectionIsFull);
+            };
+
+            keyexprs.insert(id, ke)?;
+            id
+        };
+
+        let msg = Declare {
+            body: DeclareBody::DeclareKeyExpr(DeclareKeyExpr {
+                id,
+                wire_expr: WireExpr::from(ke),
+            }),
+            ..Default::default()
+        };
+
+        if let Err(e) = driver.send(msg).await {
+            self.keyexprs.lock().await.remove(id)?;
+            return Err(e);
+        }
+
+        Ok(id)
+    }
+
+    pub(crate) async fn undeclare_keyexpr(
+        &self,
+        driver: &Driver<'res, Config>,
+        id: u16,
+    ) -> crate::ZResult<()> {
+        if !self.keyexprs.lock().await.release(id) {
+            return Ok(());
+        }
+
+        let msg = Declare {
+            body: DeclareBody::UndeclareKeyExpr(UndeclareKeyExpr { id }),
+            ..Default::default()
+        };
+
+        driver.send(msg).await
+    }
+
+    pub(crate) async fn undeclare_queryable(
+        &self,
+        driver: &Driver<'res, Config>,
+        id: u32,
+    ) -> crate::ZResult<()> {
+        self.queryable_callbacks.lock().await.remove(id)?;
+
+        let msg = Declare {
+            body: DeclareBody::UndeclareQueryable(UndeclareQueryable {
+                id,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        driver.send(msg).await
+    }
+
+    pub(crate) async fn declare_interest(
+        &self,
+        driver: &Driver<'res, Config>,
+        id: u32,
+        mode: InterestMode,
+        kind: EntityKind,
+        ke: &keyexpr,
+        wire_expr: WireExpr<'_>,
+    ) -> crate::ZResult<()> {
+        self.interests.lock().await.insert(id, mode, kind, ke)?;
+
+        let options = match kind {
+            EntityKind::Subscriber => InterestOptions::SUBSCRIBERS,
+            EntityKind::Queryable => InterestOptions::QUERYABLES,
+            EntityKind::Token => InterestOptions::TOKENS,
+        };
+
+        let msg = Interest {
+            id,
+            mode,
+            inner: InterestInner {
+                options: InterestOptions::KEYEXPRS.options | options.options,
+                wire_expr: Some(wire_expr),
+            },
+            ..Default::default()
+        };
+
+        if let Err(e) = driver.send(msg).await {
+            self.interests.lock().await.remove(id);
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn undeclare_interest(
+        &self,
+        driver: &Driver<'res, Config>,
+        id: u32,
+    ) -> crate::ZResult<()> {
+        match self.interests.lock().await.remove(id) {
+            Some(InterestMode::Future | InterestMode::CurrentFuture) => {}
+            _ => return Ok(()),
+        }
+
+        let msg = InterestFinal {
+            id,
+            ..Default::default()
+        };
+
+        driver.send(msg).await
+    }
+
+    pub(crate) async fn complete_get(&self, id: u32, liveliness: bool, reason: CompletionReason) {
+        let mut completion = if liveliness {
+            let _ = self.liveliness_get_callbacks.lock().await.remove(id);
+            self.liveliness_completion_callbacks.lock().await
+        } else {
+            let _ = self.get_callbacks.lock().await.remove(id);
+            self.consolidation.lock().await.remove(id);
+            self.completion_callbacks.lock().await
+        };
+
+        self.get_waiters.lock(|waiters| waiters.borrow_mut().wake());
+
+        if let Some(cb) = completion.get(id) {
+            cb.call(&reason).await;
+        }
+
+        let _ = completion.remove(id);
+    }
+
+    /// Drops the state of the get `id` without completing it.
+    pub(crate) async fn forget_get(&self, id: u32, liveliness: bool) {
+        if liveliness {
+            let _ = self.liveliness_get_callbacks.lock().await.remove(id);
+            let _ = self.liveliness_completion_callbacks.lock().await.remove(id);
+        } else {
+            let _ = self.get_callbacks.lock().await.remove(id);
+            self.consolidation.lock().await.remove(id);
+            let _ = self.completion_callbacks.lock().await.remove(id);
+        }
+    }
+
+    pub(crate) async fn expire_gets(&self) {
+        loop {
+            let Some(id) = self.get_callbacks.lock().await.expired() else {
+                break;
+            };
+
+            self.complete_get(id, false, CompletionReason::Timeout)
+                .await;
+        }
+
+        loop {
+            let Some(id) = self.liveliness_get_callbacks.lock().await.expired() else {
+                break;
+            };
+
+            self.complete_get(id, true, CompletionReason::Timeout).await;
+
+            // No `DeclareFinal` will come to remove the interest
+            self.interests.lock().await.remove(id);
+        }
+    }
+
+    /// Whether `id` is a liveliness get still waiting for its `DeclareFinal`.
+    pub(crate) async fn liveliness_get_pending(&self, id: u32) -> bool {
+        self.liveliness_get_callbacks.lock().await.get(id).is_some()
+            || self
+                .liveliness_completion_callbacks
+                .lock()
+                .await
+                .get(id)
+                .is_some()
+    }
+
+    pub(crate) async fn next_get_timedout(&self) -> Option<Instant> {
+        let gets = self.get_callbacks.lock().await.next_timedout();
+        let liveliness = self.liveliness_get_callbacks.lock().await.next_timedout();
+
+        match (gets, liveliness) {
+            (Some(gets), Some(liveliness)) => Some(gets.min(liveliness)),
+            (gets, liveliness) => gets.or(liveliness),
+        }
+    }
+
+    /// Resolves once the get `id` is no longer pending.
+    pub(crate) async fn get_completed(&self, id: u32, liveliness: bool) {
+        poll_fn(|cx| {
+            let pending = if liveliness {
+                self.liveliness_get_callbacks
+                    .try_lock()
+                    .map(|mut gets| gets.get(id).is_some())
+            } else {
+                self.get_callbacks
+                    .try_lock()
+                    .map(|mut gets| gets.get(id).is_some())
+            };
+
+            match pending {
+                Ok(false) => Poll::Ready(()),
+                // The callbacks may stay locked for a whole user callback, but `complete_get`
+                // wakes the waiters once the get is removed
+                Ok(true) | Err(_) => {
+                    self.get_waiters
+                        .lock(|waiters| waiters.borrow_mut().register(cx.waker()));
+                    Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+
+    pub(crate) async fn resolve<'s>(
+        &self,
+        wire_expr: &WireExpr<'s>,
+        scratch: &'s mut [u8],
+    ) -> crate::ZResult<&'s keyexpr> {
+        if wire_expr.scope == 0 {
+            return Ok(keyexpr::new(wire_expr.suffix)?);
+        }
+
+        let keyexprs = match wire_expr.mapping {
+            Mapping::Receiver => self.keyexprs.lock().await,
+            Mapping::Sender => self.remote_keyexprs.lock().await,
+        };
+
+        let Some(prefix) = keyexprs.get(wire_expr.scope) else {
+            crate::zbail!(crate::CollectionError::KeyNotFound);
+        };
+
+        let (prefix, suffix) = (prefix.as_bytes(), wire_expr.suffix.as_bytes());
+        let len = prefix.len() + suffix.len();
+        if len > scratch.len() {
+            crate::zbail!(crate::BytesError::DstIsTooSmall);
+        }
+
+        scratch[..prefix.len()].copy_from_slice(prefix);
+        scratch[prefix.len()..len].copy_from_slice(suffix);
+
+        let ke = core::str::from_utf8(&scratch[..len])
+            .map_err(|_| crate::CodecError::CouldNotParseField)?;
+
+        Ok(keyexpr::new(ke)?)
+    }
 }
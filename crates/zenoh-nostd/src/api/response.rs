@@ -2,6 +2,14 @@ use zenoh_proto::{keyexpr, zerror::CollectionError};
 
 use crate::{OwnedSample, api::Sample};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionReason {
+    /// The router sent a `ResponseFinal`, no more responses will arrive.
+    Final,
+    /// The local timeout expired before the query was finalized.
+    Timeout,
+}
+
 #[derive(Debug)]
 pub enum Response<'a> {
     Ok(Sample<'a>),
@@ -1,31 +1,114 @@
 use core::str::FromStr;
 
-use zenoh_proto::{keyexpr, zerror::CollectionError};
+use zenoh_proto::{
+    exts::SourceInfo,
+    fields::{Encoding, Timestamp},
+    keyexpr,
+    zerror::CollectionError,
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SampleKind {
+    #[default]
+    Put,
+    Delete,
+}
 
 #[derive(Debug)]
 pub struct Sample<'a> {
     ke: &'a keyexpr,
     payload: &'a [u8],
+    kind: SampleKind,
+    encoding: Encoding<'a>,
+    timestamp: Option<Timestamp>,
+    source_info: Option<SourceInfo>,
+    attachment: Option<&'a [u8]>,
 }
 
 impl<'a> Sample<'a> {
     pub fn new(ke: &'a keyexpr, payload: &'a [u8]) -> Self {
-        Self { ke, payload }
+        Self {
+            ke,
+            payload,
+            kind: SampleKind::Put,
+            encoding: Encoding::default(),
+            timestamp: None,
+            source_info: None,
+            attachment: None,
+        }
+    }
+
+    pub fn delete(ke: &'a keyexpr) -> Self {
+        Self {
+            ke,
+            payload: &[],
+            kind: SampleKind::Delete,
+            encoding: Encoding::default(),
+            timestamp: None,
+            source_info: None,
+            attachment: None,
+        }
+    }
+
+    pub(crate) fn with_encoding(mut self, encoding: Encoding<'a>) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    pub(crate) fn with_timestamp(mut self, timestamp: Option<Timestamp>) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    pub(crate) fn with_source_info(mut self, source_info: Option<SourceInfo>) -> Self {
+        self.source_info = source_info;
+        self
+    }
+
+    pub(crate) fn with_attachment(mut self, attachment: Option<&'a [u8]>) -> Self {
+        self.attachment = attachment;
+        self
     }
 
     pub fn keyexpr(&self) -> &keyexpr {
         self.ke
     }
 
+    pub fn kind(&self) -> SampleKind {
+        self.kind
+    }
+
     pub fn payload(&self) -> &[u8] {
         self.payload
     }
+
+    pub fn encoding(&self) -> &Encoding<'a> {
+        &self.encoding
+    }
+
+    pub fn timestamp(&self) -> Option<&Timestamp> {
+        self.timestamp.as_ref()
+    }
+
+    /// The publishing entity and its sequence number, if the sender attached them.
+    pub fn source_info(&self) -> Option<&SourceInfo> {
+        self.source_info.as_ref()
+    }
+
+    pub fn attachment(&self) -> Option<&[u8]> {
+        self.attachment
+    }
 }
 
 #[derive(Debug)]
 pub struct OwnedSample<const MAX_KEYEXPR: usize, const MAX_PAYLOAD: usize> {
     ke: heapless::String<MAX_KEYEXPR>,
     payload: heapless::Vec<u8, MAX_PAYLOAD>,
+    kind: SampleKind,
+    // The encoding schema and the attachment are not kept
+    encoding: u16,
+    timestamp: Option<Timestamp>,
+    source_info: Option<SourceInfo>,
 }
 
 impl<const MAX_KEYEXPR: usize, const MAX_PAYLOAD: usize> OwnedSample<MAX_KEYEXPR, MAX_PAYLOAD> {
@@ -37,10 +120,34 @@ impl<const MAX_KEYEXPR: usize, const MAX_PAYLOAD: usize> OwnedSample<MAX_KEYEXPR
         self.payload.as_slice()
     }
 
+    pub fn kind(&self) -> SampleKind {
+        self.kind
+    }
+
+    pub fn encoding(&self) -> Encoding<'_> {
+        Encoding {
+            id: self.encoding,
+            schema: None,
+        }
+    }
+
+    pub fn timestamp(&self) -> Option<&Timestamp> {
+        self.timestamp.as_ref()
+    }
+
+    pub fn source_info(&self) -> Option<&SourceInfo> {
+        self.source_info.as_ref()
+    }
+
     pub fn as_ref(&self) -> Sample<'_> {
         Sample {
             ke: self.keyexpr(),
             payload: self.payload(),
+            kind: self.kind,
+            encoding: self.encoding(),
+            timestamp: self.timestamp,
+            source_info: self.source_info.clone(),
+            attachment: None,
         }
     }
 }
@@ -56,6 +163,10 @@ impl<const MAX_KEYEXPR: usize, const MAX_PAYLOAD: usize> TryFrom<&Sample<'_>>
                 .map_err(|_| CollectionError::CollectionTooSmall)?,
             payload: heapless::Vec::from_slice(value.payload())
                 .map_err(|_| CollectionError::CollectionTooSmall)?,
+            kind: value.kind(),
+            encoding: value.encoding().id,
+            timestamp: value.timestamp,
+            source_info: value.source_info.clone(),
         })
     }
 }
@@ -6,9 +6,13 @@ use crate::{
     },
 };
 
-use embassy_time::Duration;
+use zenoh_proto::fields::Timestamp;
 
+pub use advanced_pub::AdvancedCache;
+
+mod advanced_pub;
 mod get;
+mod liveliness;
 mod r#pub;
 mod put;
 mod querier;
@@ -32,6 +36,11 @@ where
 
         todo!("implement a `session.close` method that should undeclare all resources")
     }
+
+    /// A new timestamp from the session HLC, `None` unless `ZConfig::timestamping` is enabled.
+    pub fn new_timestamp(&self) -> Option<Timestamp> {
+        self.resources.new_timestamp()
+    }
 }
 
 /// Create a session bounded to the lifetimes of the `zenoh_nocore::Resources`.
@@ -43,21 +52,38 @@ pub async fn open<'res, Config>(
 where
     Config: ZConfig,
 {
+    if config.keep_alive() == 0 {
+        crate::zbail!(
+            crate::SessionError::InvalidConfig,
+            "keep_alive must be at least 1"
+        );
+    }
+
     let link = Link::new(config.platform(), endpoint).await?;
 
+    #[cfg(feature = "compression")]
+    {
+        resources.compression = config.compression_bufs();
+    }
+    #[cfg(feature = "compression")]
+    let compression = config.compression() && resources.compression.is_some();
+    #[cfg(not(feature = "compression"))]
+    let compression = config.compression();
+
+    let mine_config = TransportMineConfig {
+        mine_zid: config.identity().zid(config.platform())?,
+        mine_lease: config.lease(),
+        mine_resolution: config.resolution(),
+        mine_batch_size: config.batch_size(),
+        mine_credentials: config.credentials(),
+        mine_lowlatency: config.lowlatency(),
+        mine_compression: compression,
+        keep_alive: config.keep_alive(),
+        open_timeout: config.open_timeout(),
+    };
+
     let (tx, rx) = config.txrx();
-    let (transport, tconfig) = Transport::open(
-        link,
-        TransportMineConfig {
-            mine_zid: Default::default(),
-            mine_lease: Duration::from_secs(20),
-            keep_alive: 4,
-            open_timeout: Duration::from_secs(5),
-        },
-        tx,
-        rx,
-    )
-    .await?;
+    let (transport, tconfig) = Transport::open(link, mine_config, tx, rx).await?;
 
     Ok(resources.init(config, transport, tconfig))
 }
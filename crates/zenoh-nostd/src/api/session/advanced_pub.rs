@@ -0,0 +1,388 @@
+use core::cell::Cell;
+
+use embassy_sync::{blocking_mutex::raw::NoopRawMutex, mutex::Mutex};
+use heapless::Deque;
+use zenoh_proto::{
+    Parameters,
+    exts::EntityGlobalId,
+    fields::{Encoding, NTP64, Timestamp},
+    keyexpr,
+};
+
+use crate::api::{
+    OwnedKeyExpr, OwnedSample, Query, Sample, ZConfig,
+    session::{
+        Session,
+        r#pub::{Publisher, PublisherBuilder},
+        put::PutBuilder,
+        queryable::Queryable,
+    },
+};
+
+/// Chunks of the cache key expression `<ke>/@adv/pub/<zid>/<eid>/_`, as in zenoh-ext.
+const KE_ADV_PREFIX: &str = "@adv";
+const KE_PUB: &str = "pub";
+const KE_EMPTY: &str = "_";
+
+/// Inclusive `start..end` range of sequence numbers requested by a history query.
+const SN_RANGE_KEY: &str = "_sn";
+/// Maximum number of samples, the most recent ones, requested by a history query.
+const MAX_SAMPLES_KEY: &str = "_max";
+
+/// The last `CAPACITY` samples of an `AdvancedPublisher`, served to late joining subscribers.
+///
+/// `MAX_KEYEXPR` must also fit the cache key expression `<ke>/@adv/pub/<zid>/<eid>/_`, which
+/// adds up to 55 bytes to `ke`: the default fits key expressions of up to 73 bytes. So must the
+/// key expressions of the session's `QueryableCallbacks`.
+///
+/// A cache serves one publisher at a time. It is emptied when that publisher is undeclared and
+/// can then be given to a new one.
+pub struct AdvancedCache<
+    const CAPACITY: usize,
+    const MAX_KEYEXPR: usize = 128,
+    const MAX_PAYLOAD: usize = 128,
+> {
+    bound: Cell<bool>,
+    samples: Mutex<NoopRawMutex, Deque<OwnedSample<MAX_KEYEXPR, MAX_PAYLOAD>, CAPACITY>>,
+}
+
+impl<const CAPACITY: usize, const MAX_KEYEXPR: usize, const MAX_PAYLOAD: usize> Default
+    for AdvancedCache<CAPACITY, MAX_KEYEXPR, MAX_PAYLOAD>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const CAPACITY: usize, const MAX_KEYEXPR: usize, const MAX_PAYLOAD: usize>
+    AdvancedCache<CAPACITY, MAX_KEYEXPR, MAX_PAYLOAD>
+{
+    pub const fn new() -> Self {
+        Self {
+            bound: Cell::new(false),
+            samples: Mutex::new(Deque::new()),
+        }
+    }
+
+    /// Drops the cached samples and frees the cache for another publisher.
+    async fn release(&self) {
+        self.samples.lock().await.clear();
+        self.bound.set(false);
+    }
+
+    async fn push(&self, sample: &Sample<'_>) {
+        let Ok(sample) = OwnedSample::try_from(sample) else {
+            crate::warn!("{}: Sample is too large to be cached", crate::zctx!());
+            return;
+        };
+
+        let mut samples = self.samples.lock().await;
+        if samples.is_full() {
+            samples.pop_front();
+        }
+
+        let _ = samples.push_back(sample);
+    }
+
+    async fn reply<Config>(&self, query: &Query<'_, '_, Config>)
+    where
+        Config: ZConfig,
+    {
+        let parameters = query.parameters();
+        let filter = HistoryFilter::new(&parameters, query.now().unwrap_or(NTP64(0)));
+
+        let samples = self.samples.lock().await;
+        let count = samples.iter().filter(|s| filter.matches(s)).count();
+        let skip = filter.max.map_or(0, |max| count.saturating_sub(max));
+
+        for sample in samples.iter().filter(|s| filter.matches(s)).skip(skip) {
+            let mut reply = query
+                .reply(sample.keyexpr(), sample.payload())
+                .encoding(sample.encoding());
+
+            if let Some(timestamp) = sample.timestamp() {
+                reply = reply.timestamp(*timestamp);
+            }
+
+            if let Some(sinfo) = sample.source_info() {
+                reply = reply.source_info(sinfo.clone());
+            }
+
+            if let Err(e) = reply.finish().await {
+                crate::warn!("{}: Couldn't reply a cached sample: {}", crate::zctx!(), e);
+            }
+        }
+    }
+}
+
+/// The `_sn`, `_time` and `_max` parameters of a history query.
+struct HistoryFilter {
+    sn: Option<(Option<u32>, Option<u32>)>,
+    time: Option<zenoh_proto::TimeRange>,
+    now: NTP64,
+    max: Option<usize>,
+}
+
+impl HistoryFilter {
+    fn new(parameters: &Parameters<'_>, now: NTP64) -> Self {
+        Self {
+            sn: parameters.get(SN_RANGE_KEY).and_then(sn_range),
+            time: parameters.time_range().and_then(Result::ok),
+            now,
+            max: parameters
+                .get(MAX_SAMPLES_KEY)
+                .and_then(|max| max.parse().ok()),
+        }
+    }
+
+    fn matches<const MAX_KEYEXPR: usize, const MAX_PAYLOAD: usize>(
+        &self,
+        sample: &OwnedSample<MAX_KEYEXPR, MAX_PAYLOAD>,
+    ) -> bool {
+        if let Some((start, end)) = self.sn {
+            let Some(sn) = sample.source_info().map(|sinfo| sinfo.sn) else {
+                return false;
+            };
+
+            if start.is_some_and(|start| sn < start) || end.is_some_and(|end| sn > end) {
+                return false;
+            }
+        }
+
+        match (&self.time, sample.timestamp()) {
+            (None, _) => true,
+            (Some(time), Some(timestamp)) => time.contains(timestamp, self.now),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+fn sn_range(value: &str) -> Option<(Option<u32>, Option<u32>)> {
+    let (start, end) = value.split_once("..")?;
+    let bound = |bound: &str| match bound {
+        "" => Some(None),
+        bound => bound.parse().ok().map(Some),
+    };
+
+    Some((bound(start)?, bound(end)?))
+}
+
+/// A `Publisher` that caches its last samples and serves them through a queryable under
+/// `<ke>/@adv/pub/<zid>/<eid>/_`, compatible with the zenoh-ext advanced subscriber.
+pub struct AdvancedPublisher<
+    'a,
+    'res,
+    Config,
+    const CAPACITY: usize,
+    const MAX_KEYEXPR: usize,
+    const MAX_PAYLOAD: usize,
+> where
+    Config: ZConfig,
+{
+    publisher: Publisher<'a, 'res, Config>,
+    cache: &'res AdvancedCache<CAPACITY, MAX_KEYEXPR, MAX_PAYLOAD>,
+    cache_keyexpr: OwnedKeyExpr<MAX_KEYEXPR>,
+    queryable: Queryable<'a, 'res, Config>,
+}
+
+impl<'a, 'res, Config, const CAPACITY: usize, const MAX_KEYEXPR: usize, const MAX_PAYLOAD: usize>
+    AdvancedPublisher<'a, 'res, Config, CAPACITY, MAX_KEYEXPR, MAX_PAYLOAD>
+where
+    Config: ZConfig,
+{
+    pub fn put<'b>(
+        &'b self,
+        payload: &'b [u8],
+    ) -> AdvancedPutBuilder<'b, 'res, Config, CAPACITY, MAX_KEYEXPR, MAX_PAYLOAD> {
+        AdvancedPutBuilder {
+            ke: self.publisher.keyexpr(),
+            put: self.publisher.put(payload),
+            cache: self.cache,
+        }
+    }
+
+    pub fn keyexpr(&self) -> &'a keyexpr {
+        self.publisher.keyexpr()
+    }
+
+    pub fn id(&self) -> EntityGlobalId {
+        self.publisher.id()
+    }
+
+    /// Undeclares the publisher and its cache queryable, then empties the cache so that another
+    /// publisher can use it.
+    pub async fn undeclare(self) -> crate::ZResult<()> {
+        let queryable = self.queryable.undeclare().await;
+        let publisher = self.publisher.undeclare().await;
+        self.cache.release().await;
+
+        queryable.and(publisher)
+    }
+
+    /// The key expression on which the cache is queried.
+    pub fn cache_keyexpr(&self) -> &keyexpr {
+        &self.cache_keyexpr
+    }
+}
+
+pub struct AdvancedPutBuilder<
+    'a,
+    'res,
+    Config,
+    const CAPACITY: usize,
+    const MAX_KEYEXPR: usize,
+    const MAX_PAYLOAD: usize,
+> where
+    Config: ZConfig,
+{
+    ke: &'a keyexpr,
+    put: PutBuilder<'a, 'res, Config>,
+    cache: &'res AdvancedCache<CAPACITY, MAX_KEYEXPR, MAX_PAYLOAD>,
+}
+
+impl<'a, 'res, Config, const CAPACITY: usize, const MAX_KEYEXPR: usize, const MAX_PAYLOAD: usize>
+    AdvancedPutBuilder<'a, 'res, Config, CAPACITY, MAX_KEYEXPR, MAX_PAYLOAD>
+where
+    Config: ZConfig,
+{
+    pub fn encoding(mut self, encoding: Encoding<'a>) -> Self {
+        self.put = self.put.encoding(encoding);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: Timestamp) -> Self {
+        self.put = self.put.timestamp(timestamp);
+        self
+    }
+
+    pub fn attachment(mut self, attachment: &'a [u8]) -> Self {
+        self.put = self.put.attachment(attachment);
+        self
+    }
+
+    pub async fn finish(mut self) -> crate::ZResult<()> {
+        // Stamp once so that the cached sample and the publication are identical, the cached
+        // sample takes a sequence number even if the publication is filtered
+        self.put.timestamp = self
+            .put
+            .timestamp
+            .or_else(|| self.put.resources.new_timestamp());
+        self.put.sinfo = self.put.take_source_info();
+
+        let sample = Sample::new(self.ke, self.put.payload)
+            .with_encoding(self.put.encoding.clone())
+            .with_timestamp(self.put.timestamp)
+            .with_source_info(self.put.sinfo.clone());
+
+        self.cache.push(&sample).await;
+
+        self.put.finish().await
+    }
+}
+
+pub struct AdvancedPublisherBuilder<
+    'a,
+    'res,
+    Config,
+    const CAPACITY: usize,
+    const MAX_KEYEXPR: usize,
+    const MAX_PAYLOAD: usize,
+> where
+    Config: ZConfig,
+{
+    session: &'a Session<'res, Config>,
+    publisher: PublisherBuilder<'a, 'res, Config>,
+    cache: &'res AdvancedCache<CAPACITY, MAX_KEYEXPR, MAX_PAYLOAD>,
+}
+
+impl<'a, 'res, Config, const CAPACITY: usize, const MAX_KEYEXPR: usize, const MAX_PAYLOAD: usize>
+    AdvancedPublisherBuilder<'a, 'res, Config, CAPACITY, MAX_KEYEXPR, MAX_PAYLOAD>
+where
+    Config: ZConfig,
+{
+    pub fn encoding(mut self, encoding: Encoding<'a>) -> Self {
+        self.publisher = self.publisher.encoding(encoding);
+        self
+    }
+
+    pub fn attachment(mut self, attachment: &'a [u8]) -> Self {
+        self.publisher = self.publisher.attachment(attachment);
+        self
+    }
+
+    pub async fn finish(
+        self,
+    ) -> crate::ZResult<AdvancedPublisher<'a, 'res, Config, CAPACITY, MAX_KEYEXPR, MAX_PAYLOAD>>
+    {
+        let (session, cache) = (self.session, self.cache);
+        if cache.bound.replace(true) {
+            // The cache already belongs to another publisher
+            crate::zbail!(crate::CollectionError::KeyAlreadyExists);
+        }
+
+        let publisher = match self.publisher.finish().await {
+            Ok(publisher) => publisher,
+            Err(e) => {
+                cache.bound.set(false);
+                return Err(e);
+            }
+        };
+
+        let declared: crate::ZResult<_> = async {
+            let id = publisher.id();
+            let ke = OwnedKeyExpr::<MAX_KEYEXPR>::format(format_args!(
+                "{}/{KE_ADV_PREFIX}/{KE_PUB}/{}/{}/{KE_EMPTY}",
+                publisher.keyexpr(),
+                id.zid,
+                id.eid
+            ))?;
+
+            let queryable = session
+                .declare_queryable(&ke)
+                .callback(async move |query| cache.reply(query).await)
+                .finish()
+                .await?;
+
+            Ok((ke, queryable))
+        }
+        .await;
+
+        match declared {
+            Ok((cache_keyexpr, queryable)) => Ok(AdvancedPublisher {
+                publisher,
+                cache,
+                cache_keyexpr,
+                queryable,
+            }),
+            Err(e) => {
+                let _ = publisher.undeclare().await;
+                cache.bound.set(false);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl<'res, Config> Session<'res, Config>
+where
+    Config: ZConfig,
+{
+    /// Declares a publisher keeping its last samples in `cache`, see `AdvancedPublisher`.
+    pub fn declare_advanced_publisher<
+        'a,
+        const CAPACITY: usize,
+        const MAX_KEYEXPR: usize,
+        const MAX_PAYLOAD: usize,
+    >(
+        &'a self,
+        ke: &'a keyexpr,
+        cache: &'res AdvancedCache<CAPACITY, MAX_KEYEXPR, MAX_PAYLOAD>,
+    ) -> AdvancedPublisherBuilder<'a, 'res, Config, CAPACITY, MAX_KEYEXPR, MAX_PAYLOAD> {
+        AdvancedPublisherBuilder {
+            session: self,
+            // History queries select samples by sequence number
+            publisher: self.declare_publisher(ke).source_info(true),
+            cache,
+        }
+    }
+}
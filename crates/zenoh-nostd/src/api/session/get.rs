@@ -1,36 +1,54 @@
 use dyn_utils::DynObject;
-use embassy_futures::select::{Either, select};
+use embassy_futures::select::{Either3, select3};
 use embassy_sync::channel::{DynamicReceiver, DynamicSender};
 use embassy_time::{Duration, Instant, Timer};
 use zenoh_proto::{exts::*, fields::*, msgs::*, *};
 
 use crate::api::{
-    ZConfig,
-    arg::ResponseRef,
+    CompletionReason, ZConfig,
+    arg::{CompletionReasonRef, ResponseRef},
     callbacks::{AsyncCallback, DynCallback, SyncCallback, ZCallbacks},
+    consolidation::ZConsolidation,
     driver::Driver,
+    interests::EntityKind,
     resources::SessionResources,
 };
 
-pub struct Responses<'res, OwnedResponse = (), const CHANNEL: bool = false> {
+pub struct Responses<'a, 'res, Config, OwnedResponse = (), const CHANNEL: bool = false>
+where
+    Config: ZConfig,
+{
+    id: u32,
+    liveliness: bool,
+    resources: &'a SessionResources<'res, Config>,
+
     timedout: Instant,
     receiver: Option<DynamicReceiver<'res, OwnedResponse>>,
 }
 
-impl<'res, OwnedResponse> Responses<'res, OwnedResponse, true> {
+impl<'a, 'res, Config, OwnedResponse> Responses<'a, 'res, Config, OwnedResponse, true>
+where
+    Config: ZConfig,
+{
     pub fn try_recv(&self) -> Option<OwnedResponse> {
         self.receiver.as_ref().unwrap().try_receive().ok()
     }
 
     pub async fn recv(&self) -> Option<OwnedResponse> {
-        match select(
+        let receiver = self.receiver.as_ref().unwrap();
+
+        match select3(
             Timer::at(self.timedout),
-            self.receiver.as_ref().unwrap().receive(),
+            receiver.receive(),
+            self.resources.get_completed(self.id, self.liveliness),
         )
         .await
         {
-            Either::First(_) => None,
-            Either::Second(v) => Some(v),
+            // A response may have been queued right before the deadline
+            Either3::First(_) => receiver.try_receive().ok(),
+            Either3::Second(v) => Some(v),
+            // Drain what was received before the `ResponseFinal`
+            Either3::Third(_) => receiver.try_receive().ok(),
         }
     }
 }
@@ -41,6 +59,14 @@ type CallbackStorage<'res, Config> =
 type FutureStorage<'res, Config> =
     <<Config as ZConfig>::GetCallbacks<'res> as ZCallbacks<'res, ResponseRef>>::Future;
 
+type CompletionCallbackStorage<'res, Config> = <<Config as ZConfig>::CompletionCallbacks<
+    'res,
+> as ZCallbacks<'res, CompletionReasonRef>>::Callback;
+
+type CompletionFutureStorage<'res, Config> = <<Config as ZConfig>::CompletionCallbacks<
+    'res,
+> as ZCallbacks<'res, CompletionReasonRef>>::Future;
+
 pub struct GetBuilder<
     'a,
     'res,
@@ -54,34 +80,58 @@ pub struct GetBuilder<
     pub(crate) driver: &'a Driver<'res, Config>,
     pub(crate) resources: &'a SessionResources<'res, Config>,
 
-    pub(crate) ke: &'static keyexpr,
+    pub(crate) ke: &'a keyexpr,
+    pub(crate) scope: Option<u16>,
+    pub(crate) liveliness: bool,
     pub(crate) parameters: Option<&'a str>,
     pub(crate) payload: Option<&'a [u8]>,
     pub(crate) timeout: Option<Duration>,
+    pub(crate) target: QueryTarget,
+    pub(crate) consolidation: ConsolidationMode,
+    pub(crate) budget: Option<u32>,
     pub(crate) callback: Option<
         DynCallback<'res, CallbackStorage<'res, Config>, FutureStorage<'res, Config>, ResponseRef>,
     >,
     pub(crate) receiver: Option<DynamicReceiver<'res, OwnedResponse>>,
+    pub(crate) on_complete: Option<
+        DynCallback<
+            'res,
+            CompletionCallbackStorage<'res, Config>,
+            CompletionFutureStorage<'res, Config>,
+            CompletionReasonRef,
+        >,
+    >,
 }
 
 impl<'a, 'res, Config> GetBuilder<'a, 'res, Config, (), false, false>
 where
     Config: ZConfig,
 {
+    pub(crate) fn liveliness(mut self) -> Self {
+        self.liveliness = true;
+        self
+    }
+
     pub(crate) fn new(
         driver: &'a Driver<'res, Config>,
         resources: &'a SessionResources<'res, Config>,
-        ke: &'static keyexpr,
+        ke: &'a keyexpr,
     ) -> Self {
         Self {
             driver,
             resources,
             ke,
+            scope: None,
+            liveliness: false,
             parameters: None,
             payload: None,
             timeout: None,
+            target: QueryTarget::default(),
+            consolidation: ConsolidationMode::None,
+            budget: None,
             callback: None,
             receiver: None,
+            on_complete: None,
         }
     }
 
@@ -93,11 +143,17 @@ where
             driver: self.driver,
             resources: self.resources,
             ke: self.ke,
+            scope: self.scope,
+            liveliness: self.liveliness,
             parameters: self.parameters,
             payload: self.payload,
             timeout: self.timeout,
+            target: self.target,
+            consolidation: self.consolidation,
+            budget: self.budget,
             callback: Some(DynObject::new(AsyncCallback::new(callback))),
             receiver: None,
+            on_complete: self.on_complete,
         }
     }
 
@@ -109,11 +165,17 @@ where
             driver: self.driver,
             resources: self.resources,
             ke: self.ke,
+            scope: self.scope,
+            liveliness: self.liveliness,
             parameters: self.parameters,
             payload: self.payload,
             timeout: self.timeout,
+            target: self.target,
+            consolidation: self.consolidation,
+            budget: self.budget,
             callback: Some(DynObject::new(SyncCallback::new(callback))),
             receiver: None,
+            on_complete: self.on_complete,
         }
     }
 
@@ -129,9 +191,14 @@ where
             driver: self.driver,
             resources: self.resources,
             ke: self.ke,
+            scope: self.scope,
+            liveliness: self.liveliness,
             parameters: self.parameters,
             payload: self.payload,
             timeout: self.timeout,
+            target: self.target,
+            consolidation: self.consolidation,
+            budget: self.budget,
             callback: Some(DynObject::new(AsyncCallback::new(
                 async move |resp: &'_ crate::Response<'_>| {
                     if let Ok(resp) = OwnedResponse::try_from(resp) {
@@ -145,6 +212,7 @@ where
                 },
             ))),
             receiver: Some(receiver),
+            on_complete: self.on_complete,
         }
     }
 }
@@ -154,8 +222,9 @@ impl<'a, 'res, Config, OwnedResponse, const READY: bool, const CHANNEL: bool>
 where
     Config: ZConfig,
 {
-    pub fn keyexpr(mut self, ke: &'static keyexpr) -> Self {
+    pub fn keyexpr(mut self, ke: &'a keyexpr) -> Self {
         self.ke = ke;
+        self.scope = None;
         self
     }
 
@@ -173,6 +242,30 @@ where
         self.timeout = Some(timeout);
         self
     }
+
+    pub fn target(mut self, target: QueryTarget) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// `Latest` is downgraded to `Monotonic`: replies are delivered as they arrive and only those
+    /// older than one already delivered for the same key are dropped, so an older reply arriving
+    /// first still reaches the callback. `Auto` consolidates like `Monotonic`, unless the
+    /// parameters hold a `_time` range.
+    pub fn consolidation(mut self, consolidation: ConsolidationMode) -> Self {
+        self.consolidation = consolidation;
+        self
+    }
+
+    pub fn budget(mut self, budget: u32) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    pub fn on_complete(mut self, callback: impl AsyncFnMut(&CompletionReason) + 'res) -> Self {
+        self.on_complete = Some(DynObject::new(AsyncCallback::new(callback)));
+        self
+    }
 }
 
 impl<'a, 'res, Config, OwnedResponse, const CHANNEL: bool>
@@ -180,22 +273,91 @@ impl<'a, 'res, Config, OwnedResponse, const CHANNEL: bool>
 where
     Config: ZConfig,
 {
-    pub async fn finish(self) -> crate::ZResult<Responses<'a, OwnedResponse, CHANNEL>> {
+    pub async fn finish(
+        self,
+    ) -> crate::ZResult<Responses<'a, 'res, Config, OwnedResponse, CHANNEL>> {
+        let ke = self.ke;
         let timedout = Instant::now() + self.timeout.unwrap_or(Duration::from_secs(30));
+        let (driver, resources) = (self.driver, self.resources);
+        let (callback, on_complete) = (self.callback, self.on_complete);
+
+        resources.expire_gets().await;
+
+        if self.liveliness {
+            let id = resources.next().await;
+
+            let declared: crate::ZResult<()> = async {
+                if let Some(callback) = callback {
+                    let mut gets = resources.liveliness_get_callbacks.lock().await;
+                    gets.insert(id, ke, Some(timedout), callback)?;
+                    resources.get_inserted.signal(());
+                }
 
-        let rid = self.resources.next().await;
+                if let Some(on_complete) = on_complete {
+                    let mut completion = resources.liveliness_completion_callbacks.lock().await;
+                    completion.insert(id, ke, None, on_complete)?;
+                }
 
-        if let Some(callback) = self.callback {
-            let mut gets = self.resources.get_callbacks.lock().await;
-            gets.drop_timedout();
-            gets.insert(rid, self.ke, Some(timedout), callback)?;
+                resources
+                    .declare_interest(
+                        driver,
+                        id,
+                        InterestMode::Current,
+                        EntityKind::Token,
+                        ke,
+                        WireExpr::from(ke),
+                    )
+                    .await
+            }
+            .await;
+
+            if let Err(e) = declared {
+                resources.forget_get(id, true).await;
+                return Err(e);
+            }
+
+            return Ok(Responses {
+                id,
+                liveliness: true,
+                resources,
+                timedout,
+                receiver: self.receiver,
+            });
         }
 
+        // Like zenoh, `Auto` keeps every reply of a query over a time range. `Latest` would hold
+        // back every reply until the end of the get, which doesn't fit the bounded tables
+        let mode = match self.consolidation {
+            ConsolidationMode::Auto
+                if self
+                    .parameters
+                    .is_some_and(|p| Parameters::from(p).time_range().is_some()) =>
+            {
+                ConsolidationMode::None
+            }
+            ConsolidationMode::Auto | ConsolidationMode::Latest => ConsolidationMode::Monotonic,
+            mode => mode,
+        };
+
+        let rid = resources.next_rid().await;
+
         let msg = Request {
             id: rid,
-            wire_expr: WireExpr::from(self.ke),
+            wire_expr: match self.scope {
+                Some(scope) => WireExpr {
+                    scope,
+                    mapping: Mapping::Sender,
+                    suffix: "",
+                },
+                None => WireExpr::from(ke),
+            },
+            target: self.target,
+            budget: self.budget.map(|budget| Budget { budget }),
+            timeout: self
+                .timeout
+                .map(|timeout| core::time::Duration::from_micros(timeout.as_micros())),
             payload: RequestBody::Query(Query {
-                consolidation: ConsolidationMode::None,
+                consolidation: mode,
                 parameters: self.parameters.unwrap_or_default(),
                 body: self.payload.map(|p| Value {
                     payload: p,
@@ -206,9 +368,38 @@ where
             ..Default::default()
         };
 
-        self.driver.send(msg).await?;
+        let sent: crate::ZResult<()> = async {
+            if let Some(callback) = callback {
+                let mut gets = resources.get_callbacks.lock().await;
+                gets.insert(rid, ke, Some(timedout), callback)?;
+                resources.get_inserted.signal(());
+
+                if mode == ConsolidationMode::Monotonic {
+                    let mut consolidation = resources.consolidation.lock().await;
+                    consolidation.drop_timedout();
+                    consolidation.insert(rid, Some(timedout))?;
+                }
+            }
+
+            if let Some(on_complete) = on_complete {
+                let mut completion = resources.completion_callbacks.lock().await;
+                completion.insert(rid, ke, None, on_complete)?;
+            }
+
+            driver.send(msg).await
+        }
+        .await;
+
+        // Nothing will complete a get whose request wasn't sent
+        if let Err(e) = sent {
+            resources.forget_get(rid, false).await;
+            return Err(e);
+        }
 
         Ok(Responses {
+            id: rid,
+            liveliness: false,
+            resources,
             timedout,
             receiver: self.receiver,
         })
@@ -219,7 +410,18 @@ impl<'res, Config> super::Session<'res, Config>
 where
     Config: ZConfig,
 {
-    pub fn get(&self, ke: &'static keyexpr) -> GetBuilder<'_, 'res, Config> {
-        GetBuilder::new(&self.driver, &self.resources, ke)
+    /// Accepts a `keyexpr` or a `Selector` such as `"sensors/**?_time=[now(-1h)..]"`.
+    pub fn get<'a, S>(&'a self, selector: S) -> crate::ZResult<GetBuilder<'a, 'res, Config>>
+    where
+        S: TryInto<Selector<'a>>,
+        S::Error: Into<crate::Error>,
+    {
+        let selector = selector.try_into().map_err(Into::into)?;
+        let builder = GetBuilder::new(&self.driver, &self.resources, selector.keyexpr());
+
+        Ok(match selector.parameters().as_str() {
+            "" => builder,
+            parameters => builder.parameters(parameters),
+        })
     }
 }
@@ -0,0 +1,134 @@
+use zenoh_proto::{fields::*, msgs::*, *};
+
+use crate::api::{
+    ZConfig,
+    driver::Driver,
+    interests::ZInterests,
+    resources::SessionResources,
+    session::{get::GetBuilder, sub::SubscriberBuilder},
+};
+
+pub struct Liveliness<'a, 'res, Config>
+where
+    Config: ZConfig,
+{
+    driver: &'a Driver<'res, Config>,
+    resources: &'a SessionResources<'res, Config>,
+}
+
+impl<'a, 'res, Config> Liveliness<'a, 'res, Config>
+where
+    Config: ZConfig,
+{
+    pub fn declare_token(&self, ke: &'a keyexpr) -> LivelinessTokenBuilder<'a, 'res, Config> {
+        LivelinessTokenBuilder {
+            driver: self.driver,
+            resources: self.resources,
+            ke,
+        }
+    }
+
+    pub fn declare_subscriber(&self, ke: &'a keyexpr) -> SubscriberBuilder<'a, 'res, Config> {
+        SubscriberBuilder::new(self.driver, self.resources, ke).liveliness()
+    }
+
+    pub fn get(&self, ke: &'a keyexpr) -> GetBuilder<'a, 'res, Config> {
+        GetBuilder::new(self.driver, self.resources, ke).liveliness()
+    }
+}
+
+pub struct LivelinessToken<'a, 'res, Config>
+where
+    Config: ZConfig,
+{
+    id: u32,
+
+    driver: &'a Driver<'res, Config>,
+    resources: &'a SessionResources<'res, Config>,
+
+    ke: &'a keyexpr,
+}
+
+impl<'a, 'res, Config> LivelinessToken<'a, 'res, Config>
+where
+    Config: ZConfig,
+{
+    pub fn keyexpr(&self) -> &keyexpr {
+        self.ke
+    }
+
+    pub async fn undeclare(self) -> crate::ZResult<()> {
+        self.resources
+            .interests
+            .lock()
+            .await
+            .undeclare_token(self.id);
+
+        let msg = Declare {
+            body: DeclareBody::UndeclareToken(UndeclareToken {
+                id: self.id,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        self.driver.send(msg).await
+    }
+}
+
+pub struct LivelinessTokenBuilder<'a, 'res, Config>
+where
+    Config: ZConfig,
+{
+    driver: &'a Driver<'res, Config>,
+    resources: &'a SessionResources<'res, Config>,
+
+    ke: &'a keyexpr,
+}
+
+impl<'a, 'res, Config> LivelinessTokenBuilder<'a, 'res, Config>
+where
+    Config: ZConfig,
+{
+    pub async fn finish(self) -> crate::ZResult<LivelinessToken<'a, 'res, Config>> {
+        let id = self.resources.next().await;
+
+        self.resources
+            .interests
+            .lock()
+            .await
+            .declare_token(id, self.ke)?;
+
+        let msg = Declare {
+            body: DeclareBody::DeclareToken(DeclareToken {
+                id,
+                wire_expr: WireExpr::from(self.ke),
+            }),
+            ..Default::default()
+        };
+
+        if let Err(e) = self.driver.send(msg).await {
+            self.resources.interests.lock().await.undeclare_token(id);
+            return Err(e);
+        }
+
+        Ok(LivelinessToken {
+            id,
+            driver: self.driver,
+            resources: self.resources,
+            ke: self.ke,
+        })
+    }
+}
+
+impl<'res, Config> super::Session<'res, Config>
+where
+    Config: ZConfig,
+{
+    pub fn liveliness(&self) -> Liveliness<'_, 'res, Config> {
+        Liveliness {
+            driver: &self.driver,
+            resources: &self.resources,
+        }
+    }
+}
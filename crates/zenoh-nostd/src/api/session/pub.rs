@@ -1,18 +1,34 @@
+use core::cell::Cell;
+
 use zenoh_proto::{
-    exts::Attachment,
-    fields::{Encoding, Timestamp},
+    exts::{Attachment, EntityGlobalId},
+    fields::{Encoding, Mapping, Timestamp, WireExpr},
     keyexpr,
+    msgs::InterestMode,
 };
 
-use crate::api::{ZConfig, driver::Driver, session::put::PutBuilder};
+use crate::api::{
+    MatchingListener, MatchingStatus, ZConfig,
+    driver::Driver,
+    interests::{EntityKind, ZInterests},
+    resources::SessionResources,
+    session::put::PutBuilder,
+};
 
 pub struct Publisher<'a, 'res, Config>
 where
     Config: ZConfig,
 {
     driver: &'a Driver<'res, Config>,
+    resources: &'a SessionResources<'res, Config>,
 
     ke: &'a keyexpr,
+    eid: u32,
+    sn: Cell<u32>,
+    scope: u16,
+    interest: u32,
+    write_filter: bool,
+    source_info: bool,
     encoding: Encoding<'a>,
     timestamp: Option<Timestamp>,
     attachment: Option<Attachment<'a>>,
@@ -22,25 +38,61 @@ impl<'a, 'res, Config> Publisher<'a, 'res, Config>
 where
     Config: ZConfig,
 {
-    pub fn put(&self, payload: &'a [u8]) -> PutBuilder<'a, 'res, Config> {
+    pub fn put<'b>(&'b self, payload: &'b [u8]) -> PutBuilder<'b, 'res, Config> {
         PutBuilder {
             driver: self.driver,
-            ke: self.ke,
+            resources: self.resources,
+            wire_expr: WireExpr {
+                scope: self.scope,
+                mapping: Mapping::Sender,
+                suffix: "",
+            },
             payload,
             encoding: self.encoding.clone(),
             timestamp: self.timestamp,
             attachment: self.attachment.clone(),
+            write_filter: self.write_filter.then_some(self.interest),
+            sinfo: None,
+            source: self.source_info.then(|| (self.id(), &self.sn)),
         }
     }
 
-    #[allow(dead_code)]
-    async fn undeclare(self) -> crate::ZResult<()> {
-        todo!("send undeclare interest")
+    /// The global id of this publisher, found in the `SourceInfo` of its samples.
+    pub fn id(&self) -> EntityGlobalId {
+        self.resources.entity_global_id(self.eid)
     }
 
-    pub fn keyexpr(&self) -> &keyexpr {
+    pub async fn undeclare(self) -> crate::ZResult<()> {
+        // Both slots are released even if the interest can't be finalized
+        let interest = self
+            .resources
+            .undeclare_interest(self.driver, self.interest)
+            .await;
+
+        self.resources
+            .undeclare_keyexpr(self.driver, self.scope)
+            .await?;
+
+        interest
+    }
+
+    pub fn keyexpr(&self) -> &'a keyexpr {
         self.ke
     }
+
+    pub async fn matching_status(&self) -> MatchingStatus {
+        let interests = self.resources.interests.lock().await;
+        MatchingStatus::new(interests.matching(self.interest).unwrap_or_default())
+    }
+
+    /// Declares a listener notified when the matching status changes. Several listeners may be
+    /// declared, each one until it is undeclared.
+    pub async fn matching_listener(
+        &self,
+        callback: impl AsyncFnMut(&MatchingStatus) + 'res,
+    ) -> crate::ZResult<MatchingListener<'a, 'res, Config>> {
+        MatchingListener::new(self.resources, self.interest, self.ke, callback).await
+    }
 }
 
 pub struct PublisherBuilder<'a, 'res, Config>
@@ -48,8 +100,11 @@ where
     Config: ZConfig,
 {
     driver: &'a Driver<'res, Config>,
+    resources: &'a SessionResources<'res, Config>,
 
     ke: &'a keyexpr,
+    write_filter: bool,
+    source_info: bool,
     encoding: Encoding<'a>,
     timestamp: Option<Timestamp>,
     attachment: Option<Attachment<'a>>,
@@ -59,10 +114,17 @@ impl<'a, 'res, Config> PublisherBuilder<'a, 'res, Config>
 where
     Config: ZConfig,
 {
-    pub(crate) fn new(driver: &'a Driver<'res, Config>, ke: &'a keyexpr) -> Self {
+    pub(crate) fn new(
+        driver: &'a Driver<'res, Config>,
+        resources: &'a SessionResources<'res, Config>,
+        ke: &'a keyexpr,
+    ) -> Self {
         Self {
             driver,
+            resources,
             ke,
+            write_filter: false,
+            source_info: false,
             encoding: Encoding::default(),
             timestamp: None,
             attachment: None,
@@ -74,6 +136,18 @@ where
         self
     }
 
+    /// Drop puts when no remote subscriber matches the key expression.
+    pub fn write_filter(mut self, write_filter: bool) -> Self {
+        self.write_filter = write_filter;
+        self
+    }
+
+    /// Attach the publisher id and a sequence number to each put, about 20 more bytes per sample.
+    pub fn source_info(mut self, source_info: bool) -> Self {
+        self.source_info = source_info;
+        self
+    }
+
     pub fn encoding(mut self, encoding: Encoding<'a>) -> Self {
         self.encoding = encoding;
         self
@@ -90,11 +164,39 @@ where
     }
 
     pub async fn finish(self) -> crate::ZResult<Publisher<'a, 'res, Config>> {
-        // TODO: send interest msg
+        let eid = self.resources.next().await;
+        let scope = self.resources.declare_keyexpr(self.driver, self.ke).await?;
+        let interest = self.resources.next().await;
+        if let Err(e) = self
+            .resources
+            .declare_interest(
+                self.driver,
+                interest,
+                InterestMode::CurrentFuture,
+                EntityKind::Subscriber,
+                self.ke,
+                WireExpr {
+                    scope,
+                    mapping: Mapping::Sender,
+                    suffix: "",
+                },
+            )
+            .await
+        {
+            let _ = self.resources.undeclare_keyexpr(self.driver, scope).await;
+            return Err(e);
+        }
 
         Ok(Publisher {
             driver: self.driver,
+            resources: self.resources,
             ke: self.ke,
+            eid,
+            sn: Cell::new(0),
+            scope,
+            interest,
+            write_filter: self.write_filter,
+            source_info: self.source_info,
             encoding: self.encoding,
             timestamp: self.timestamp,
             attachment: self.attachment,
@@ -107,6 +209,6 @@ where
     Config: ZConfig,
 {
     pub fn declare_publisher<'a>(&'a self, ke: &'a keyexpr) -> PublisherBuilder<'a, 'res, Config> {
-        PublisherBuilder::new(&self.driver, ke)
+        PublisherBuilder::new(&self.driver, &self.resources, ke)
     }
 }
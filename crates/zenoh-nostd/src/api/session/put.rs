@@ -1,19 +1,27 @@
+use core::cell::Cell;
+
 use zenoh_proto::{exts::*, fields::*, msgs::*, *};
 
-use crate::api::{ZConfig, driver::Driver};
+use crate::api::{ZConfig, driver::Driver, interests::ZInterests, resources::SessionResources};
 
 pub struct PutBuilder<'a, 'res, Config>
 where
     Config: ZConfig,
 {
     pub(crate) driver: &'a Driver<'res, Config>,
+    pub(crate) resources: &'a SessionResources<'res, Config>,
 
-    pub(crate) ke: &'a keyexpr,
+    pub(crate) wire_expr: WireExpr<'a>,
     pub(crate) payload: &'a [u8],
 
     pub(crate) encoding: Encoding<'a>,
     pub(crate) timestamp: Option<Timestamp>,
     pub(crate) attachment: Option<Attachment<'a>>,
+
+    pub(crate) write_filter: Option<u32>,
+    pub(crate) sinfo: Option<SourceInfo>,
+    /// The publisher and its next sequence number, only taken once the put isn't filtered.
+    pub(crate) source: Option<(EntityGlobalId, &'a Cell<u32>)>,
 }
 
 impl<'a, 'res, Config> PutBuilder<'a, 'res, Config>
@@ -22,16 +30,21 @@ where
 {
     pub(crate) fn new(
         driver: &'a Driver<'res, Config>,
+        resources: &'a SessionResources<'res, Config>,
         ke: &'a keyexpr,
         payload: &'a [u8],
     ) -> Self {
         Self {
             driver,
-            ke,
+            resources,
+            wire_expr: WireExpr::from(ke),
             payload,
             encoding: Encoding::default(),
             timestamp: None,
             attachment: None,
+            write_filter: None,
+            sinfo: None,
+            source: None,
         }
     }
 
@@ -55,17 +68,37 @@ where
         self
     }
 
-    pub async fn finish(self) -> crate::ZResult<()> {
+    /// Attaches the next sequence number of the publisher, so that subscribers can detect misses.
+    pub(crate) fn take_source_info(&mut self) -> Option<SourceInfo> {
+        let (id, sn) = self.source.take()?;
+        let next = sn.get();
+        sn.set(next.wrapping_add(1));
+
+        Some(SourceInfo { id, sn: next })
+    }
+
+    pub async fn finish(mut self) -> crate::ZResult<()> {
+        if let Some(interest) = self.write_filter {
+            let interests = self.resources.interests.lock().await;
+            // Only filter once the router has answered with all its current subscribers
+            if interests.is_final(interest) && interests.matching(interest) == Some(false) {
+                return Ok(());
+            }
+        }
+
+        let timestamp = self.timestamp.or_else(|| self.resources.new_timestamp());
+        let sinfo = self.sinfo.take().or_else(|| self.take_source_info());
+
         let msg = Push {
-            wire_expr: WireExpr::from(self.ke),
+            wire_expr: self.wire_expr,
             payload: PushBody::Put(Put {
                 payload: self.payload,
                 encoding: self.encoding,
-                timestamp: self.timestamp,
+                timestamp,
+                sinfo,
                 attachment: self.attachment,
-                ..Default::default()
             }),
-            timestamp: self.timestamp,
+            timestamp,
             ..Default::default()
         };
 
@@ -78,6 +111,6 @@ where
     Config: ZConfig,
 {
     pub fn put<'a>(&'a self, ke: &'a keyexpr, payload: &'a [u8]) -> PutBuilder<'a, 'res, Config> {
-        PutBuilder::new(&self.driver, ke, payload)
+        PutBuilder::new(&self.driver, &self.resources, ke, payload)
     }
 }
@@ -1,7 +1,18 @@
 use embassy_time::Duration;
-use zenoh_proto::keyexpr;
+use zenoh_proto::{
+    exts::QueryTarget,
+    fields::{ConsolidationMode, Mapping, WireExpr},
+    keyexpr,
+    msgs::InterestMode,
+};
 
-use crate::api::{ZConfig, driver::Driver, resources::SessionResources, session::get::GetBuilder};
+use crate::api::{
+    MatchingListener, MatchingStatus, ZConfig,
+    driver::Driver,
+    interests::{EntityKind, ZInterests},
+    resources::SessionResources,
+    session::get::GetBuilder,
+};
 
 pub struct Querier<'a, 'res, Config>
 where
@@ -10,10 +21,15 @@ where
     driver: &'a Driver<'res, Config>,
     resources: &'a SessionResources<'res, Config>,
 
-    ke: &'static keyexpr,
+    ke: &'a keyexpr,
+    scope: u16,
+    interest: u32,
     parameters: Option<&'a str>,
     payload: Option<&'a [u8]>,
     timeout: Option<Duration>,
+    target: QueryTarget,
+    consolidation: ConsolidationMode,
+    budget: Option<u32>,
 }
 
 impl<'a, 'res, Config> Querier<'a, 'res, Config>
@@ -25,22 +41,51 @@ where
             driver: self.driver,
             resources: self.resources,
             ke: self.ke,
+            scope: Some(self.scope),
+            liveliness: false,
             parameters: self.parameters,
             payload: self.payload,
             timeout: self.timeout,
+            target: self.target,
+            consolidation: self.consolidation,
+            budget: self.budget,
             callback: None,
             receiver: None,
+            on_complete: None,
         }
     }
 
-    #[allow(dead_code)]
-    async fn undeclare(self) -> crate::ZResult<()> {
-        todo!("send undeclare interest")
+    pub async fn undeclare(self) -> crate::ZResult<()> {
+        // Both slots are released even if the interest can't be finalized
+        let interest = self
+            .resources
+            .undeclare_interest(self.driver, self.interest)
+            .await;
+
+        self.resources
+            .undeclare_keyexpr(self.driver, self.scope)
+            .await?;
+
+        interest
     }
 
     pub fn keyexpr(&self) -> &keyexpr {
         self.ke
     }
+
+    pub async fn matching_status(&self) -> MatchingStatus {
+        let interests = self.resources.interests.lock().await;
+        MatchingStatus::new(interests.matching(self.interest).unwrap_or_default())
+    }
+
+    /// Declares a listener notified when the matching status changes. Several listeners may be
+    /// declared, each one until it is undeclared.
+    pub async fn matching_listener(
+        &self,
+        callback: impl AsyncFnMut(&MatchingStatus) + 'res,
+    ) -> crate::ZResult<MatchingListener<'a, 'res, Config>> {
+        MatchingListener::new(self.resources, self.interest, self.ke, callback).await
+    }
 }
 
 pub struct QuerierBuilder<'a, 'res, Config>
@@ -50,10 +95,13 @@ where
     driver: &'a Driver<'res, Config>,
     resources: &'a SessionResources<'res, Config>,
 
-    ke: &'static keyexpr,
+    ke: &'a keyexpr,
     parameters: Option<&'a str>,
     payload: Option<&'a [u8]>,
     timeout: Option<Duration>,
+    target: QueryTarget,
+    consolidation: ConsolidationMode,
+    budget: Option<u32>,
 }
 
 impl<'a, 'res, Config> QuerierBuilder<'a, 'res, Config>
@@ -63,7 +111,7 @@ where
     pub(crate) fn new(
         driver: &'a Driver<'res, Config>,
         resources: &'a SessionResources<'res, Config>,
-        ke: &'static keyexpr,
+        ke: &'a keyexpr,
     ) -> Self {
         Self {
             driver,
@@ -72,6 +120,9 @@ where
             parameters: None,
             payload: None,
             timeout: None,
+            target: QueryTarget::default(),
+            consolidation: ConsolidationMode::None,
+            budget: None,
         }
     }
 
@@ -90,15 +141,57 @@ where
         self
     }
 
+    pub fn target(mut self, target: QueryTarget) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// `Latest` is downgraded to `Monotonic`, see `GetBuilder::consolidation`.
+    pub fn consolidation(mut self, consolidation: ConsolidationMode) -> Self {
+        self.consolidation = consolidation;
+        self
+    }
+
+    pub fn budget(mut self, budget: u32) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
     pub async fn finish(self) -> crate::ZResult<Querier<'a, 'res, Config>> {
-        // TODO: send interest msg
+        let scope = self.resources.declare_keyexpr(self.driver, self.ke).await?;
+        let interest = self.resources.next().await;
+        if let Err(e) = self
+            .resources
+            .declare_interest(
+                self.driver,
+                interest,
+                InterestMode::CurrentFuture,
+                EntityKind::Queryable,
+                self.ke,
+                WireExpr {
+                    scope,
+                    mapping: Mapping::Sender,
+                    suffix: "",
+                },
+            )
+            .await
+        {
+            let _ = self.resources.undeclare_keyexpr(self.driver, scope).await;
+            return Err(e);
+        }
+
         Ok(Querier {
             driver: self.driver,
             resources: self.resources,
             ke: self.ke,
+            scope,
+            interest,
             parameters: self.parameters,
             payload: self.payload,
             timeout: self.timeout,
+            target: self.target,
+            consolidation: self.consolidation,
+            budget: self.budget,
         })
     }
 }
@@ -107,7 +200,7 @@ impl<'res, Config> super::Session<'res, Config>
 where
     Config: ZConfig,
 {
-    pub fn declare_querier(&self, ke: &'static keyexpr) -> QuerierBuilder<'_, 'res, Config> {
+    pub fn declare_querier<'a>(&'a self, ke: &'a keyexpr) -> QuerierBuilder<'a, 'res, Config> {
         QuerierBuilder::new(&self.driver, &self.resources, ke)
     }
 }
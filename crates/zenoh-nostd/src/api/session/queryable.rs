@@ -1,109 +1,104 @@
 use dyn_utils::DynObject;
 use embassy_sync::channel::{DynamicReceiver, DynamicSender};
-use zenoh_proto::{fields::*, msgs::*, *};
+use zenoh_proto::{exts::EntityGlobalId, fields::*, msgs::*, *};
 
 use crate::api::{
-    ZConfig,
+    ReceivedQuery, ZConfig,
     arg::QueryRef,
-    callbacks::{AsyncCallback, DynCallback, SyncCallback, ZCallbacks},
+    callbacks::{AsyncQueryCallback, DynCallback, SyncQueryCallback, ZCallbacks},
     driver::Driver,
     resources::SessionResources,
 };
 
-pub struct Queryable<Config, OwnedQuery = (), const CHANNEL: bool = false>
+pub struct Queryable<'a, 'res, Config, OwnedQuery = (), const CHANNEL: bool = false>
 where
     Config: ZConfig,
-    OwnedQuery: 'static,
 {
-    driver: &'static Driver<'static, Config>,
-    resources: &'static SessionResources<'static, Config>,
+    driver: &'a Driver<'res, Config>,
+    resources: &'a SessionResources<'res, Config>,
 
     id: u32,
-    receiver: Option<DynamicReceiver<'static, OwnedQuery>>,
+    receiver: Option<DynamicReceiver<'res, OwnedQuery>>,
 }
 
-impl<Config, OwnedQuery, const CHANNEL: bool> Queryable<Config, OwnedQuery, CHANNEL>
+impl<'a, 'res, Config, OwnedQuery, const CHANNEL: bool>
+    Queryable<'a, 'res, Config, OwnedQuery, CHANNEL>
 where
     Config: ZConfig,
 {
-    #[allow(dead_code)]
-    async fn undeclare(self) -> crate::ZResult<()> {
-        let msg = Declare {
-            body: DeclareBody::UndeclareQueryable(UndeclareQueryable {
-                id: self.id,
-                ..Default::default()
-            }),
-            ..Default::default()
-        };
+    /// The global id of this queryable, found in the `respid` of its replies.
+    pub fn id(&self) -> EntityGlobalId {
+        self.resources.entity_global_id(self.id)
+    }
 
+    pub async fn undeclare(self) -> crate::ZResult<()> {
         self.resources
-            .queryable_callbacks
-            .lock()
+            .undeclare_queryable(self.driver, self.id)
             .await
-            .remove(self.id)?;
-
-        self.driver.send(msg).await?;
-
-        todo!("Also stop the channel if any")
     }
 }
 
-impl<Config, OwnedQuery> Queryable<Config, OwnedQuery, true>
+impl<'a, 'res, Config, OwnedQuery> Queryable<'a, 'res, Config, OwnedQuery, true>
 where
     Config: ZConfig,
 {
-    pub fn try_recv(&self) -> Option<OwnedQuery> {
-        self.receiver.as_ref().unwrap().try_receive().ok()
+    pub fn try_recv(&self) -> Option<ReceivedQuery<'a, 'res, Config, OwnedQuery>> {
+        let query = self.receiver.as_ref().unwrap().try_receive().ok()?;
+        Some(ReceivedQuery::new(self.driver, self.resources, query))
     }
 
-    pub async fn recv(&self) -> Option<OwnedQuery> {
-        Some(self.receiver.as_ref().unwrap().receive().await)
+    pub async fn recv(&self) -> Option<ReceivedQuery<'a, 'res, Config, OwnedQuery>> {
+        let query = self.receiver.as_ref().unwrap().receive().await;
+        Some(ReceivedQuery::new(self.driver, self.resources, query))
     }
 }
 
-type CallbackStorage<Config> = <<Config as ZConfig>::QueryableCallbacks<'static> as ZCallbacks<
-    'static,
-    QueryRef<'static, Config>,
+type CallbackStorage<'res, Config> = <<Config as ZConfig>::QueryableCallbacks<'res> as ZCallbacks<
+    'res,
+    QueryRef<'res, Config>,
 >>::Callback;
 
-type FutureStorage<Config> = <<Config as ZConfig>::QueryableCallbacks<'static> as ZCallbacks<
-    'static,
-    QueryRef<'static, Config>,
+type FutureStorage<'res, Config> = <<Config as ZConfig>::QueryableCallbacks<'res> as ZCallbacks<
+    'res,
+    QueryRef<'res, Config>,
 >>::Future;
 
 pub struct QueryableBuilder<
+    'a,
+    'k,
+    'res,
     Config,
     OwnedQuery = (),
     const READY: bool = false,
     const CHANNEL: bool = false,
 > where
     Config: ZConfig,
-    OwnedQuery: 'static,
 {
-    driver: &'static Driver<'static, Config>,
-    resources: &'static SessionResources<'static, Config>,
+    driver: &'a Driver<'res, Config>,
+    resources: &'a SessionResources<'res, Config>,
 
-    ke: &'static keyexpr,
+    /// Only read when declaring, the queryable doesn't borrow it.
+    ke: &'k keyexpr,
 
     callback: Option<
         DynCallback<
-            'static,
-            CallbackStorage<Config>,
-            FutureStorage<Config>,
-            QueryRef<'static, Config>,
+            'res,
+            CallbackStorage<'res, Config>,
+            FutureStorage<'res, Config>,
+            QueryRef<'res, Config>,
         >,
     >,
-    receiver: Option<DynamicReceiver<'static, OwnedQuery>>,
+    receiver: Option<DynamicReceiver<'res, OwnedQuery>>,
 }
 
-impl<Config> QueryableBuilder<Config, (), false, false>
+impl<'a, 'k, 'res, Config> QueryableBuilder<'a, 'k, 'res, Config, (), false, false>
 where
     Config: ZConfig,
 {
     pub(crate) fn new(
-        driver: &'static Driver<'static, Config>,
-        resources: &'static SessionResources<'static, Config>,
-        ke: &'static keyexpr,
+        driver: &'a Driver<'res, Config>,
+        resources: &'a SessionResources<'res, Config>,
+        ke: &'k keyexpr,
     ) -> Self {
         Self {
             driver,
@@ -116,58 +111,47 @@ where
 
     pub fn callback(
         self,
-        callback: impl AsyncFnMut(&crate::Query<'_, 'static, Config>) + 'static,
-    ) -> QueryableBuilder<Config, (), true, false> {
+        callback: impl AsyncFnMut(&crate::Query<'_, 'res, Config>) + 'res,
+    ) -> QueryableBuilder<'a, 'k, 'res, Config, (), true, false> {
         QueryableBuilder {
             driver: self.driver,
             resources: self.resources,
             ke: self.ke,
-            callback: Some(DynObject::new(AsyncCallback::new(callback))),
+            callback: Some(DynObject::new(AsyncQueryCallback::new(callback))),
             receiver: None,
         }
     }
 
     pub fn callback_sync(
         self,
-        callback: impl FnMut(&crate::Query<'_, 'static, Config>) + 'static,
-    ) -> QueryableBuilder<Config, (), true, false> {
+        callback: impl FnMut(&crate::Query<'_, 'res, Config>) + 'res,
+    ) -> QueryableBuilder<'a, 'k, 'res, Config, (), true, false> {
         QueryableBuilder {
             driver: self.driver,
             resources: self.resources,
             ke: self.ke,
-            callback: Some(DynObject::new(SyncCallback::new(callback))),
+            callback: Some(DynObject::new(SyncQueryCallback::new(callback))),
             receiver: None,
         }
     }
-}
 
-impl<Config> QueryableBuilder<Config, (), false, false>
-where
-    Config: ZConfig,
-{
     pub fn channel<OwnedQuery, E>(
         self,
-        sender: DynamicSender<'static, OwnedQuery>,
-        receiver: DynamicReceiver<'static, OwnedQuery>,
-    ) -> QueryableBuilder<Config, OwnedQuery, true, true>
+        sender: DynamicSender<'res, OwnedQuery>,
+        receiver: DynamicReceiver<'res, OwnedQuery>,
+    ) -> QueryableBuilder<'a, 'k, 'res, Config, OwnedQuery, true, true>
     where
-        OwnedQuery: for<'any> TryFrom<
-                (
-                    &'any crate::Query<'any, 'static, Config>,
-                    &'static Driver<'static, Config>,
-                    &'static SessionResources<'static, Config>,
-                ),
-                Error = E,
-            >,
+        OwnedQuery: for<'any> TryFrom<&'any crate::Query<'any, 'res, Config>, Error = E>,
     {
         QueryableBuilder {
             driver: self.driver,
             resources: self.resources,
             ke: self.ke,
-            callback: Some(DynObject::new(AsyncCallback::new(
-                async move |resp: &'_ crate::Query<'_, 'static, Config>| {
-                    if let Ok(resp) = OwnedQuery::try_from((resp, self.driver, self.resources)) {
-                        sender.send(resp).await;
+            callback: Some(DynObject::new(AsyncQueryCallback::new(
+                async move |query: &'_ crate::Query<'_, 'res, Config>| {
+                    if let Ok(owned) = OwnedQuery::try_from(query) {
+                        query.hand_off();
+                        sender.send(owned).await;
                     } else {
                         crate::error!(
                             "{}: Couldn't convert to a transferable query",
@@ -181,11 +165,12 @@ where
     }
 }
 
-impl<Config, OwnedQuery, const CHANNEL: bool> QueryableBuilder<Config, OwnedQuery, true, CHANNEL>
+impl<'a, 'k, 'res, Config, OwnedQuery, const CHANNEL: bool>
+    QueryableBuilder<'a, 'k, 'res, Config, OwnedQuery, true, CHANNEL>
 where
     Config: ZConfig,
 {
-    pub async fn finish(self) -> crate::ZResult<Queryable<Config, OwnedQuery, CHANNEL>> {
+    pub async fn finish(self) -> crate::ZResult<Queryable<'a, 'res, Config, OwnedQuery, CHANNEL>> {
         let id = self.resources.next().await;
 
         if let Some(callback) = self.callback {
@@ -203,7 +188,10 @@ where
             ..Default::default()
         };
 
-        self.driver.send(msg).await?;
+        if let Err(e) = self.driver.send(msg).await {
+            let _ = self.resources.queryable_callbacks.lock().await.remove(id);
+            return Err(e);
+        }
 
         Ok(Queryable {
             id,
@@ -214,11 +202,14 @@ where
     }
 }
 
-impl<Config> super::Session<'static, Config>
+impl<'res, Config> super::Session<'res, Config>
 where
     Config: ZConfig,
 {
-    pub fn declare_queryable(&'static self, ke: &'static keyexpr) -> QueryableBuilder<Config> {
+    pub fn declare_queryable<'a, 'k>(
+        &'a self,
+        ke: &'k keyexpr,
+    ) -> QueryableBuilder<'a, 'k, 'res, Config> {
         QueryableBuilder::new(&self.driver, &self.resources, ke)
     }
 }
@@ -7,6 +7,7 @@ use crate::api::{
     arg::SampleRef,
     callbacks::{AsyncCallback, DynCallback, SyncCallback, ZCallbacks},
     driver::Driver,
+    interests::EntityKind,
     resources::SessionResources,
 };
 
@@ -15,6 +16,7 @@ where
     Config: ZConfig,
 {
     id: u32,
+    liveliness: bool,
 
     driver: &'a Driver<'res, Config>,
     resources: &'a SessionResources<'res, Config>,
@@ -27,21 +29,31 @@ impl<'a, 'res, Config, OwnedSample, const CHANNEL: bool>
 where
     Config: ZConfig,
 {
-    #[allow(dead_code)]
-    async fn undeclare(self) -> crate::ZResult<()> {
-        let msg = Declare {
-            body: DeclareBody::UndeclareSubscriber(UndeclareSubscriber {
-                id: self.id,
+    /// Undeclares the subscriber. A liveliness subscriber also releases its interest.
+    pub async fn undeclare(self) -> crate::ZResult<()> {
+        if self.liveliness {
+            self.resources
+                .liveliness_callbacks
+                .lock()
+                .await
+                .remove(self.id)?;
+
+            self.resources
+                .undeclare_interest(self.driver, self.id)
+                .await
+        } else {
+            let msg = Declare {
+                body: DeclareBody::UndeclareSubscriber(UndeclareSubscriber {
+                    id: self.id,
+                    ..Default::default()
+                }),
                 ..Default::default()
-            }),
-            ..Default::default()
-        };
-
-        self.resources.sub_callbacks.lock().await.remove(self.id)?;
+            };
 
-        self.driver.send(msg).await?;
+            self.resources.sub_callbacks.lock().await.remove(self.id)?;
 
-        todo!("Also stop the channel if any")
+            self.driver.send(msg).await
+        }
     }
 }
 
@@ -77,7 +89,8 @@ pub struct SubscriberBuilder<
     driver: &'a Driver<'res, Config>,
     resources: &'a SessionResources<'res, Config>,
 
-    ke: &'static keyexpr,
+    ke: &'a keyexpr,
+    liveliness: bool,
 
     callback: Option<
         DynCallback<'res, CallbackStorage<'res, Config>, FutureStorage<'res, Config>, SampleRef>,
@@ -89,15 +102,21 @@ impl<'a, 'res, Config> SubscriberBuilder<'a, 'res, Config, (), false, false>
 where
     Config: ZConfig,
 {
+    pub(crate) fn liveliness(mut self) -> Self {
+        self.liveliness = true;
+        self
+    }
+
     pub(crate) fn new(
         driver: &'a Driver<'res, Config>,
         resources: &'a SessionResources<'res, Config>,
-        ke: &'static keyexpr,
+        ke: &'a keyexpr,
     ) -> Self {
         Self {
             driver,
             resources,
             ke,
+            liveliness: false,
             callback: None,
             receiver: None,
         }
@@ -111,6 +130,7 @@ where
             driver: self.driver,
             resources: self.resources,
             ke: self.ke,
+            liveliness: self.liveliness,
             callback: Some(DynObject::new(AsyncCallback::new(callback))),
             receiver: None,
         }
@@ -124,6 +144,7 @@ where
             driver: self.driver,
             resources: self.resources,
             ke: self.ke,
+            liveliness: self.liveliness,
             callback: Some(DynObject::new(SyncCallback::new(callback))),
             receiver: None,
         }
@@ -141,6 +162,7 @@ where
             driver: self.driver,
             resources: self.resources,
             ke: self.ke,
+            liveliness: self.liveliness,
             callback: Some(DynObject::new(AsyncCallback::new(
                 async move |resp: &'_ crate::Sample<'_>| {
                     if let Ok(resp) = OwnedSample::try_from(resp) {
@@ -169,23 +191,49 @@ where
         let id = self.resources.next().await;
 
         if let Some(callback) = self.callback {
-            let mut subs = self.resources.sub_callbacks.lock().await;
+            let mut subs = match self.liveliness {
+                true => self.resources.liveliness_callbacks.lock().await,
+                false => self.resources.sub_callbacks.lock().await,
+            };
             subs.drop_timedout();
             subs.insert(id, self.ke, None, callback)?;
         }
 
-        let msg = Declare {
-            body: DeclareBody::DeclareSubscriber(DeclareSubscriber {
-                id,
-                wire_expr: WireExpr::from(self.ke),
-            }),
-            ..Default::default()
+        let declared = if self.liveliness {
+            self.resources
+                .declare_interest(
+                    self.driver,
+                    id,
+                    InterestMode::Future,
+                    EntityKind::Token,
+                    self.ke,
+                    WireExpr::from(self.ke),
+                )
+                .await
+        } else {
+            let msg = Declare {
+                body: DeclareBody::DeclareSubscriber(DeclareSubscriber {
+                    id,
+                    wire_expr: WireExpr::from(self.ke),
+                }),
+                ..Default::default()
+            };
+
+            self.driver.send(msg).await
         };
 
-        self.driver.send(msg).await?;
+        if let Err(e) = declared {
+            let mut subs = match self.liveliness {
+                true => self.resources.liveliness_callbacks.lock().await,
+                false => self.resources.sub_callbacks.lock().await,
+            };
+            let _ = subs.remove(id);
+            return Err(e);
+        }
 
         Ok(Subscriber {
             id,
+            liveliness: self.liveliness,
             driver: self.driver,
             resources: self.resources,
             receiver: self.receiver,
@@ -197,7 +245,10 @@ impl<'res, Config> super::Session<'res, Config>
 where
     Config: ZConfig,
 {
-    pub fn declare_subscriber(&self, ke: &'static keyexpr) -> SubscriberBuilder<'_, 'res, Config> {
+    pub fn declare_subscriber<'a>(
+        &'a self,
+        ke: &'a keyexpr,
+    ) -> SubscriberBuilder<'a, 'res, Config> {
         SubscriberBuilder::new(&self.driver, &self.resources, ke)
     }
 }
@@ -7,12 +7,25 @@ use crate::{
     platform::ZPlatform,
 };
 
+#[cfg(feature = "compression")]
+mod compression;
 mod establishment;
 
+#[derive(Clone, Copy)]
+pub struct Credentials {
+    pub user: &'static str,
+    pub password: &'static str,
+}
+
 #[derive(Clone)]
 pub struct TransportMineConfig {
     pub mine_zid: ZenohIdProto,
     pub mine_lease: Duration,
+    pub mine_resolution: Resolution,
+    pub mine_batch_size: u16,
+    pub mine_credentials: Option<Credentials>,
+    pub mine_lowlatency: bool,
+    pub mine_compression: bool,
 
     pub keep_alive: usize,
     pub open_timeout: Duration,
@@ -34,6 +47,8 @@ pub struct TransportNegociatedConfig {
 
     pub resolution: Resolution,
     pub batch_size: u16,
+    pub compression: bool,
+    pub lowlatency: bool,
 }
 
 #[derive(Clone)]
@@ -75,7 +90,20 @@ where
     pub fn split(&mut self) -> (TransportTx<'_, Platform>, TransportRx<'_, Platform>) {
         let (link_tx, link_rx) = self.link.split();
 
-        (TransportTx { tx: link_tx }, TransportRx { rx: link_rx })
+        (
+            TransportTx {
+                tx: link_tx,
+                lowlatency: false,
+                #[cfg(feature = "compression")]
+                compression: None,
+            },
+            TransportRx {
+                rx: link_rx,
+                lowlatency: false,
+                #[cfg(feature = "compression")]
+                compression: None,
+            },
+        )
     }
 }
 
@@ -84,6 +112,9 @@ where
     Platform: ZPlatform,
 {
     tx: LinkTx<'a, Platform>,
+    lowlatency: bool,
+    #[cfg(feature = "compression")]
+    compression: Option<&'a mut [u8]>,
 }
 
 pub struct TransportRx<'a, Platform>
@@ -91,25 +122,85 @@ where
     Platform: ZPlatform,
 {
     rx: LinkRx<'a, Platform>,
+    lowlatency: bool,
+    #[cfg(feature = "compression")]
+    compression: Option<&'a mut [u8]>,
+}
+
+impl<'a, Platform> TransportTx<'a, Platform>
+where
+    Platform: ZPlatform,
+{
+    pub fn with_lowlatency(mut self, lowlatency: bool) -> Self {
+        self.lowlatency = lowlatency;
+        self
+    }
+
+    #[cfg(feature = "compression")]
+    pub fn with_compression(mut self, scratch: &'a mut [u8]) -> Self {
+        self.compression = Some(scratch);
+        self
+    }
+}
+
+impl<'a, Platform> TransportRx<'a, Platform>
+where
+    Platform: ZPlatform,
+{
+    pub fn with_lowlatency(mut self, lowlatency: bool) -> Self {
+        self.lowlatency = lowlatency;
+        self
+    }
+
+    #[cfg(feature = "compression")]
+    pub fn with_compression(mut self, scratch: &'a mut [u8]) -> Self {
+        self.compression = Some(scratch);
+        self
+    }
 }
 
 pub trait ZTransportTx {
     fn tx(&mut self) -> &mut impl ZLinkTx;
 
+    fn lowlatency(&self) -> bool {
+        false
+    }
+
+    #[cfg(feature = "compression")]
+    fn compression(&mut self) -> Option<&mut [u8]> {
+        None
+    }
+
     fn send(
         &mut self,
         tx: &mut [u8],
         sn: &mut u32,
+        resolution: Bits,
         mut writer: impl FnMut(
             &mut BatchWriter<&mut [u8]>,
         ) -> core::result::Result<(), crate::CodecError>,
     ) -> impl core::future::Future<Output = core::result::Result<(), crate::TransportError>> {
+        #[cfg(feature = "compression")]
+        let header = self.compression().map_or(0, |_| 1);
+        #[cfg(not(feature = "compression"))]
+        let header = 0;
+
+        let lowlatency = self.lowlatency();
+
         let (mut batch, space) = if self.tx().is_streamed() {
             let space = u16::MIN.to_le_bytes();
             tx[..space.len()].copy_from_slice(&space);
-            (BatchWriter::new(&mut tx[space.len()..], *sn), space.len())
+            (
+                BatchWriter::with_resolution(&mut tx[space.len() + header..], *sn, resolution)
+                    .lowlatency(lowlatency),
+                space.len(),
+            )
         } else {
-            (BatchWriter::new(&mut tx[..], *sn), 0)
+            (
+                BatchWriter::with_resolution(&mut tx[header..], *sn, resolution)
+                    .lowlatency(lowlatency),
+                0,
+            )
         };
 
         let res = writer(&mut batch);
@@ -117,6 +208,12 @@ pub trait ZTransportTx {
         let (next_sn, payload_len) = batch.finalize();
         *sn = next_sn;
 
+        #[cfg(feature = "compression")]
+        let payload_len = match self.compression() {
+            Some(scratch) => compression::compress(&mut tx[space..], payload_len, scratch),
+            None => payload_len,
+        };
+
         if self.tx().is_streamed() {
             let len_bytes = (payload_len as u16).to_le_bytes();
             tx[..space].copy_from_slice(&len_bytes);
@@ -136,6 +233,15 @@ pub trait ZTransportTx {
 pub trait ZTransportRx {
     fn rx(&mut self) -> &mut impl ZLinkRx;
 
+    fn lowlatency(&self) -> bool {
+        false
+    }
+
+    #[cfg(feature = "compression")]
+    fn compression(&mut self) -> Option<&mut [u8]> {
+        None
+    }
+
     fn recv<'a>(
         &mut self,
         rx: &'a mut [u8],
@@ -154,6 +260,11 @@ pub trait ZTransportRx {
                 self.rx().read(rx.as_mut()).await?
             };
 
+            #[cfg(feature = "compression")]
+            if let Some(scratch) = self.compression() {
+                return compression::decompress(rx, n, scratch);
+            }
+
             let slice: &'a [u8] = &rx[..n];
 
             Ok(slice)
@@ -168,6 +279,15 @@ where
     fn tx(&mut self) -> &mut impl ZLinkTx {
         &mut self.tx
     }
+
+    fn lowlatency(&self) -> bool {
+        self.lowlatency
+    }
+
+    #[cfg(feature = "compression")]
+    fn compression(&mut self) -> Option<&mut [u8]> {
+        self.compression.as_deref_mut()
+    }
 }
 
 impl<Platform> ZTransportRx for TransportRx<'_, Platform>
@@ -177,6 +297,15 @@ where
     fn rx(&mut self) -> &mut impl ZLinkRx {
         &mut self.rx
     }
+
+    fn lowlatency(&self) -> bool {
+        self.lowlatency
+    }
+
+    #[cfg(feature = "compression")]
+    fn compression(&mut self) -> Option<&mut [u8]> {
+        self.compression.as_deref_mut()
+    }
 }
 
 impl<Platform> ZTransportTx for Transport<Platform>
@@ -0,0 +1,96 @@
+use zenoh_proto::zbail;
+
+const BATCH_HEADER_COMPRESSION: u8 = 0b0000_0001;
+
+/// Compresses the `len` bytes following the batch header in place. Falls back to the
+/// uncompressed payload when compression does not shrink it. Returns the length of the
+/// batch including its header.
+pub(super) fn compress(batch: &mut [u8], len: usize, scratch: &mut [u8]) -> usize {
+    match lz4_flex::block::compress_into(&batch[1..1 + len], scratch) {
+        Ok(n) if n < len => {
+            batch[1..1 + n].copy_from_slice(&scratch[..n]);
+            batch[0] = BATCH_HEADER_COMPRESSION;
+            1 + n
+        }
+        _ => {
+            batch[0] = 0;
+            1 + len
+        }
+    }
+}
+
+pub(super) fn decompress<'a>(
+    rx: &'a mut [u8],
+    len: usize,
+    scratch: &mut [u8],
+) -> core::result::Result<&'a [u8], crate::TransportError> {
+    if len == 0 {
+        zbail!(crate::TransportError::InvalidRx);
+    }
+
+    if rx[0] & BATCH_HEADER_COMPRESSION == 0 {
+        let slice: &'a [u8] = &rx[1..len];
+        return Ok(slice);
+    }
+
+    let n = match lz4_flex::block::decompress_into(&rx[1..len], scratch) {
+        Ok(n) if n <= rx.len() => n,
+        _ => zbail!(
+            crate::TransportError::InvalidRx,
+            "Could not decompress a batch of {} bytes",
+            len
+        ),
+    };
+
+    rx[..n].copy_from_slice(&scratch[..n]);
+
+    let slice: &'a [u8] = &rx[..n];
+    Ok(slice)
+}
+
+#[test]
+fn compression_roundtrip() {
+    let payload: [u8; 256] = core::array::from_fn(|i| b"zenoh/"[i % 6]);
+
+    let mut batch = [0u8; 512];
+    let mut scratch = [0u8; 512];
+    batch[1..1 + payload.len()].copy_from_slice(&payload);
+
+    let len = compress(&mut batch, payload.len(), &mut scratch);
+    assert!(len < 1 + payload.len());
+    assert_eq!(batch[0], BATCH_HEADER_COMPRESSION);
+
+    let decompressed = decompress(&mut batch, len, &mut scratch).unwrap();
+    assert_eq!(decompressed, &payload);
+}
+
+#[test]
+fn compression_fallback() {
+    let payload: [u8; 16] = core::array::from_fn(|i| (i as u8).wrapping_mul(97));
+
+    let mut batch = [0u8; 64];
+    let mut scratch = [0u8; 64];
+    batch[1..1 + payload.len()].copy_from_slice(&payload);
+
+    let len = compress(&mut batch, payload.len(), &mut scratch);
+    assert_eq!(len, 1 + payload.len());
+    assert_eq!(batch[0], 0);
+
+    let decompressed = decompress(&mut batch, len, &mut scratch).unwrap();
+    assert_eq!(decompressed, &payload);
+}
+
+#[test]
+fn compression_invalid() {
+    let mut scratch = [0u8; 64];
+
+    assert!(decompress(&mut [0u8; 8], 0, &mut scratch).is_err());
+    assert!(
+        decompress(
+            &mut [BATCH_HEADER_COMPRESSION, 0xff, 0xff, 0xff],
+            4,
+            &mut scratch
+        )
+        .is_err()
+    );
+}
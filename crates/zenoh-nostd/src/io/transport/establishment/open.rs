@@ -2,18 +2,36 @@ use crate::{
     io::{
         link::{Link, ZLinkInfo},
         transport::{
-            Transport, TransportConfig, TransportMineConfig, TransportNegociatedConfig,
-            TransportOtherConfig, ZTransportRx, ZTransportTx, establishment::compute_sn,
+            Credentials, Transport, TransportConfig, TransportMineConfig,
+            TransportNegociatedConfig, TransportOtherConfig, ZTransportRx, ZTransportTx,
+            establishment::{compute_hmac, compute_sn},
         },
     },
     platform::ZPlatform,
 };
 use embassy_time::Duration;
-use zenoh_proto::{exts::Patch, fields::*, msgs::*, zbail, *};
+use zenoh_proto::{exts::*, fields::*, msgs::*, zbail, *};
+
+const USRPWD_AUTH_SIZE: usize = 256;
+
+/// Frame SNs and request IDs are `u32`, so a 64-bit resolution is proposed as 32 bits.
+fn downgrade(mut resolution: Resolution) -> Resolution {
+    for field in [Field::FrameSN, Field::RequestID] {
+        if resolution.get(field) == Bits::U64 {
+            resolution.set(field, Bits::U32);
+        }
+    }
+
+    resolution
+}
 
 pub(crate) struct StateTransport {
     pub(crate) batch_size: u16,
     pub(crate) resolution: Resolution,
+    pub(crate) credentials: Option<Credentials>,
+    pub(crate) nonce: u64,
+    pub(crate) compression: bool,
+    pub(crate) lowlatency: bool,
 }
 
 pub(crate) struct SendInitSynIn {
@@ -29,6 +47,12 @@ impl SendInitSynIn {
         transport: &mut impl ZTransportTx,
         state: &StateTransport,
     ) -> core::result::Result<(), crate::TransportError> {
+        let mut auth = [0u8; USRPWD_AUTH_SIZE];
+        let auth = match state.credentials {
+            Some(_) => Some(HasUsrPwd {}.to_auth(&mut auth)?),
+            None => None,
+        };
+
         let msg = InitSyn {
             version: self.mine_version,
             identifier: InitIdentifier {
@@ -39,12 +63,20 @@ impl SendInitSynIn {
                 resolution: state.resolution,
                 batch_size: BatchSize(state.batch_size),
             },
+            auth,
+            lowlatency: state.lowlatency.then_some(HasLowLatency {}),
+            compression: state.compression.then_some(HasCompression {}),
             patch: Patch::current(),
             ..Default::default()
         };
 
         transport
-            .send(tx.as_mut(), &mut 0, |batch| batch.unframed(&msg))
+            .send(
+                tx.as_mut(),
+                &mut 0,
+                state.resolution.get(Field::FrameSN),
+                |batch| batch.unframed(&msg),
+            )
             .await
     }
 }
@@ -96,6 +128,19 @@ impl<'a> RecvInitAckOut<'a> {
         };
 
         state.batch_size = state.batch_size.min(init_ack.resolution.batch_size.0);
+        state.compression = state.compression && init_ack.compression.is_some();
+        state.lowlatency = state.lowlatency && init_ack.lowlatency.is_some();
+
+        if state.credentials.is_some() {
+            let Some(nonce) = init_ack.auth.as_ref().and_then(UsrPwdNonce::from_auth) else {
+                zbail!(
+                    crate::TransportError::AuthFailed,
+                    "Received InitAck without a usrpwd nonce"
+                );
+            };
+
+            state.nonce = nonce.nonce;
+        }
 
         let output = RecvInitAckOut {
             other_zid: init_ack.identifier.zid,
@@ -123,18 +168,38 @@ impl<'a> SendOpenSynIn<'a> {
     ) -> core::result::Result<SendOpenSynOut, crate::TransportError> {
         let mine_initial_sn = compute_sn(&self.mine_zid, &self.other_zid, state.resolution);
 
+        let mut auth = [0u8; USRPWD_AUTH_SIZE];
+        let auth = match state.credentials {
+            Some(credentials) => {
+                let hmac = compute_hmac(state.nonce, credentials.password);
+                let proof = UsrPwdProof {
+                    user: credentials.user.as_bytes(),
+                    hmac: &hmac,
+                };
+
+                Some(proof.to_auth(&mut auth)?)
+            }
+            None => None,
+        };
+
         let msg = OpenSyn {
             lease: self.mine_lease.into(),
             sn: mine_initial_sn,
             cookie: self.other_cookie,
+            auth,
             ..Default::default()
         };
 
         transport
-            .send(tx.as_mut(), &mut 0, |batch| {
-                batch.unframed(&msg)?;
-                Ok(())
-            })
+            .send(
+                tx.as_mut(),
+                &mut 0,
+                state.resolution.get(Field::FrameSN),
+                |batch| {
+                    batch.unframed(&msg)?;
+                    Ok(())
+                },
+            )
             .await?;
 
         let output = SendOpenSynOut {
@@ -159,6 +224,7 @@ impl RecvOpenAckOut {
     pub(crate) async fn recv(
         rx: &mut impl AsMut<[u8]>,
         transport: &mut impl ZTransportRx,
+        state: &StateTransport,
     ) -> core::result::Result<Self, crate::TransportError> {
         let reader = transport.recv(rx.as_mut()).await?;
         let mut batch = BatchReader::new(reader);
@@ -169,6 +235,19 @@ impl RecvOpenAckOut {
                 None => zbail!(crate::TransportError::InvalidRx),
             }
         };
+
+        if state.credentials.is_some()
+            && open_ack
+                .auth
+                .as_ref()
+                .and_then(HasUsrPwd::from_auth)
+                .is_none()
+        {
+            zbail!(
+                crate::TransportError::AuthFailed,
+                "Received OpenAck without a usrpwd acknowledgment"
+            );
+        }
         let output = RecvOpenAckOut {
             other_sn: open_ack.sn,
             other_lease: open_ack
@@ -187,13 +266,20 @@ pub(crate) async fn open_link<Platform: ZPlatform>(
     tx: &mut impl AsMut<[u8]>,
     rx: &mut impl AsMut<[u8]>,
 ) -> core::result::Result<(Transport<Platform>, TransportConfig), crate::TransportError> {
-    let batch_size = link.mtu().min(rx.as_mut().len() as u16);
+    let batch_size = link
+        .mtu()
+        .min(rx.as_mut().len() as u16)
+        .min(config.mine_batch_size);
 
     let mut transport = Transport { link };
 
     let mut state = StateTransport {
         batch_size,
-        resolution: Resolution::default(),
+        resolution: downgrade(config.mine_resolution),
+        credentials: config.mine_credentials,
+        nonce: 0,
+        compression: cfg!(feature = "compression") && config.mine_compression,
+        lowlatency: config.mine_lowlatency,
     };
 
     let isyn_in = SendInitSynIn {
@@ -216,7 +302,7 @@ pub(crate) async fn open_link<Platform: ZPlatform>(
     };
 
     let osyn_out = osyn_in.send(tx, &mut transport, &state).await?;
-    let oack_out = RecvOpenAckOut::recv(rx, &mut transport).await?;
+    let oack_out = RecvOpenAckOut::recv(rx, &mut transport, &state).await?;
 
     Ok((
         transport,
@@ -232,6 +318,8 @@ pub(crate) async fn open_link<Platform: ZPlatform>(
                 mine_sn: osyn_out.mine_sn,
                 batch_size: state.batch_size,
                 resolution: state.resolution,
+                compression: state.compression,
+                lowlatency: state.lowlatency,
             },
         },
     ))
@@ -9,3 +9,6 @@ mod io;
 pub use api::*;
 pub mod platform;
 pub use zenoh_proto::{debug, error, info, logging, trace, warn, zbail, zctx, zerror::*};
+
+#[cfg(test)]
+mod tests;
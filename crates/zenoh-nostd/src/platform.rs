@@ -1,4 +1,7 @@
-use core::net::SocketAddr;
+use core::{net::SocketAddr, time::Duration};
+
+use embassy_time::Instant;
+use zenoh_proto::fields::{NTP64, ZenohIdProto};
 
 pub mod tcp;
 pub mod udp;
@@ -9,6 +12,30 @@ pub trait ZPlatform {
     type UdpSocket: udp::ZUdpSocket;
     type WebSocket: ws::ZWebSocket;
 
+    /// Fills `buf` with random bytes from the platform entropy source, only needed by
+    /// `Identity::Random`. The default draws them like `ZenohIdProto::default()`, through
+    /// `getrandom`.
+    fn fill_random(&self, buf: &mut [u8]) -> core::result::Result<(), crate::SessionError> {
+        for chunk in buf.chunks_mut(16) {
+            let bytes = ZenohIdProto::default().as_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+
+        Ok(())
+    }
+
+    /// Offset from the `embassy_time::Instant` origin to the UNIX epoch, if a wall clock is available.
+    fn unix_time_offset(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Physical time of the session clock: UNIX time with a wall clock, time since boot otherwise.
+    fn now(&self) -> NTP64 {
+        let now = Duration::from_micros(Instant::now().as_micros());
+
+        NTP64::from(now + self.unix_time_offset().unwrap_or_default())
+    }
+
     fn new_tcp_stream(
         &self,
         addr: &SocketAddr,
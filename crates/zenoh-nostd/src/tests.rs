@@ -0,0 +1,5 @@
+mod consolidation;
+mod hlc;
+mod ke;
+mod router;
+mod session;
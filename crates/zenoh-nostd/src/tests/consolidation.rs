@@ -0,0 +1,96 @@
+use embassy_time::{Duration, Instant};
+use zenoh_proto::fields::{NTP64, Timestamp, ZenohIdProto};
+
+use crate::{
+    CollectionError, FixedCapacityConsolidation, api::consolidation::ZConsolidation, keyexpr,
+};
+
+fn ke(ke: &str) -> &keyexpr {
+    keyexpr::new(ke).unwrap()
+}
+
+fn at(zid: &ZenohIdProto, time: u64) -> Timestamp {
+    zid.timestamp(NTP64(time))
+}
+
+#[test]
+fn older_and_equal_replies() {
+    let zid = ZenohIdProto::default();
+    let mut consolidation = FixedCapacityConsolidation::<2>::empty();
+    consolidation.insert(1, None).unwrap();
+
+    assert!(consolidation.accept(1, ke("robot/1"), Some(&at(&zid, 10))));
+    assert!(!consolidation.accept(1, ke("robot/1"), Some(&at(&zid, 5))));
+    assert!(!consolidation.accept(1, ke("robot/1"), Some(&at(&zid, 10))));
+    assert!(consolidation.accept(1, ke("robot/1"), Some(&at(&zid, 11))));
+    assert!(!consolidation.accept(1, ke("robot/1"), Some(&at(&zid, 10))));
+
+    // Keys and requests are consolidated apart
+    assert!(consolidation.accept(1, ke("robot/2"), Some(&at(&zid, 5))));
+    assert!(consolidation.accept(2, ke("robot/1"), Some(&at(&zid, 5))));
+}
+
+#[test]
+fn untimestamped_replies() {
+    let zid = ZenohIdProto::default();
+    let mut consolidation = FixedCapacityConsolidation::<2>::empty();
+    consolidation.insert(1, None).unwrap();
+
+    assert!(consolidation.accept(1, ke("robot/1"), None));
+    assert!(consolidation.accept(1, ke("robot/1"), Some(&at(&zid, 10))));
+    assert!(consolidation.accept(1, ke("robot/1"), None));
+    assert!(!consolidation.accept(1, ke("robot/1"), Some(&at(&zid, 10))));
+}
+
+#[test]
+fn table_full() {
+    let zid = ZenohIdProto::default();
+    let mut consolidation = FixedCapacityConsolidation::<2, 2, 8>::empty();
+
+    consolidation.insert(1, None).unwrap();
+    assert_eq!(
+        consolidation.insert(1, None),
+        Err(CollectionError::KeyAlreadyExists)
+    );
+    consolidation.insert(2, None).unwrap();
+    assert_eq!(
+        consolidation.insert(3, None),
+        Err(CollectionError::CollectionIsFull)
+    );
+
+    assert!(consolidation.accept(1, ke("a/1"), Some(&at(&zid, 10))));
+    assert!(consolidation.accept(1, ke("a/2"), Some(&at(&zid, 10))));
+
+    // Keys that don't fit are forwarded as-is
+    assert!(consolidation.accept(1, ke("a/3"), Some(&at(&zid, 10))));
+    assert!(consolidation.accept(1, ke("a/3"), Some(&at(&zid, 5))));
+    assert!(consolidation.accept(2, ke("robot/1/pose"), Some(&at(&zid, 10))));
+    assert!(consolidation.accept(2, ke("robot/1/pose"), Some(&at(&zid, 5))));
+
+    assert!(!consolidation.accept(1, ke("a/2"), Some(&at(&zid, 5))));
+}
+
+#[test]
+fn timed_out_requests() {
+    let zid = ZenohIdProto::default();
+    let mut consolidation = FixedCapacityConsolidation::<2>::empty();
+
+    consolidation.insert(1, Some(Instant::now())).unwrap();
+    consolidation
+        .insert(2, Some(Instant::now() + Duration::from_secs(60)))
+        .unwrap();
+
+    assert!(consolidation.accept(1, ke("robot/1"), Some(&at(&zid, 10))));
+    assert!(consolidation.accept(2, ke("robot/1"), Some(&at(&zid, 10))));
+
+    consolidation.drop_timedout();
+
+    // The timed-out request doesn't consolidate anymore and frees its slot
+    assert!(!consolidation.remove(1));
+    assert!(consolidation.accept(1, ke("robot/1"), Some(&at(&zid, 5))));
+    assert!(!consolidation.accept(2, ke("robot/1"), Some(&at(&zid, 5))));
+    consolidation.insert(3, None).unwrap();
+
+    assert!(consolidation.remove(2));
+    assert!(consolidation.accept(2, ke("robot/1"), Some(&at(&zid, 5))));
+}
@@ -0,0 +1,94 @@
+use core::{cell::Cell, time::Duration};
+
+use zenoh_proto::fields::{NTP64, ZenohIdProto};
+
+use crate::{
+    api::hlc::Hlc,
+    platform::{ZPlatform, tcp::DummyTcpStream, udp::DummyUdpSocket, ws::DummyWsStream},
+};
+
+/// A physical clock that only moves when told to.
+struct Clock(Cell<NTP64>);
+
+impl Clock {
+    fn new(now: u64) -> Self {
+        Self(Cell::new(NTP64(now)))
+    }
+
+    fn advance(&self, by: Duration) {
+        self.0.set(self.0.get() + NTP64::from(by));
+    }
+}
+
+impl ZPlatform for Clock {
+    type TcpStream = DummyTcpStream;
+    type UdpSocket = DummyUdpSocket;
+    type WebSocket = DummyWsStream;
+
+    fn now(&self) -> NTP64 {
+        self.0.get()
+    }
+}
+
+#[test]
+fn counter_bits() {
+    let clock = Clock::new(0x1234_5678_9abc_def7);
+    let hlc = Hlc::new(&clock, ZenohIdProto::default());
+
+    // The counter bits of the physical time are cleared, then count the timestamps of a tick
+    assert_eq!(
+        hlc.new_timestamp().get_time().as_u64(),
+        0x1234_5678_9abc_def0
+    );
+    assert_eq!(
+        hlc.new_timestamp().get_time().as_u64(),
+        0x1234_5678_9abc_def1
+    );
+    assert_eq!(
+        hlc.new_timestamp().get_time().as_u64(),
+        0x1234_5678_9abc_def2
+    );
+
+    // A new tick resets the counter
+    clock.advance(Duration::from_millis(1));
+    assert_eq!(hlc.new_timestamp().get_time().as_u64() & 0xF, 0);
+}
+
+#[test]
+fn stalled_clock() {
+    let clock = Clock::new(1 << 40);
+    let hlc = Hlc::new(&clock, ZenohIdProto::default());
+
+    let mut last = hlc.new_timestamp();
+    for _ in 0..64 {
+        let timestamp = hlc.new_timestamp();
+        assert!(timestamp > last);
+        last = timestamp;
+    }
+
+    // The clock went backwards
+    clock.0.set(NTP64(1 << 39));
+    assert!(hlc.new_timestamp() > last);
+}
+
+#[test]
+fn update_max_delta() {
+    let clock = Clock::new(1 << 40);
+    let zid = ZenohIdProto::default();
+    let hlc = Hlc::new(&clock, zid.clone());
+    let now = clock.now();
+
+    // Too far in the future, ignored
+    hlc.update(&zid.timestamp(now + NTP64::from(Duration::from_secs(1))));
+    assert_eq!(*hlc.new_timestamp().get_time(), now);
+
+    // Within the delta, the clock moves past it
+    let ahead = now + NTP64::from(Duration::from_millis(100));
+    hlc.update(&zid.timestamp(ahead));
+    assert!(*hlc.new_timestamp().get_time() > ahead);
+
+    // In the past, the clock doesn't go back
+    let last = hlc.new_timestamp();
+    hlc.update(&zid.timestamp(now));
+    assert!(hlc.new_timestamp() > last);
+}
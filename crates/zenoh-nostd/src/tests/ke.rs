@@ -0,0 +1,144 @@
+use std::panic::catch_unwind;
+
+use crate::{CollectionError, KeFormat, KeyexprError, OwnedKeyExpr, ZResult, keyexpr};
+
+crate::kedefine!(robot_pose: "robot/$id/pose", robot_link: "robot/$id/link/$id");
+
+#[test]
+fn owned_join_concat() {
+    let ke = OwnedKeyExpr::<16>::new("robot/1").unwrap();
+
+    assert_eq!(ke.join("pose").unwrap().as_str(), "robot/1/pose");
+    assert_eq!(ke.concat("2").unwrap().as_str(), "robot/12");
+    assert_eq!(ke.join("**").unwrap().as_str(), "robot/1/**");
+
+    assert!(ke.join("").is_err());
+    assert!(ke.join("a#").is_err());
+    assert!(ke.concat("/").is_err());
+    assert!(OwnedKeyExpr::<16>::new("robot/*/**/*").is_err());
+}
+
+#[test]
+fn owned_capacity() {
+    let ke = OwnedKeyExpr::<8>::new("robot/1").unwrap();
+
+    assert_eq!(
+        ke.join("pose").err(),
+        Some(CollectionError::CollectionTooSmall.into())
+    );
+    assert_eq!(
+        OwnedKeyExpr::<4>::new("robot").err(),
+        Some(CollectionError::CollectionTooSmall.into())
+    );
+    assert_eq!(
+        OwnedKeyExpr::<4>::try_from(keyexpr::new("robot").unwrap()).err(),
+        Some(CollectionError::CollectionTooSmall)
+    );
+}
+
+#[test]
+fn format_parse() {
+    let ke: OwnedKeyExpr<32> = crate::keformat!(robot_pose, id = 42).unwrap();
+    assert_eq!(ke.as_str(), "robot/42/pose");
+
+    let parsed = robot_pose.parse(&ke).unwrap();
+    assert_eq!(parsed.keyexpr(), &*ke);
+    assert_eq!(parsed.get("id"), Some("42"));
+    assert_eq!(parsed.get("name"), None);
+    assert!(parsed.iter().eq([("id", "42")]));
+
+    assert!(robot_pose.placeholders().eq(["id"]));
+    assert!(
+        robot_pose
+            .parse(keyexpr::new("robot/42").unwrap())
+            .is_none()
+    );
+    assert!(
+        robot_pose
+            .parse(keyexpr::new("robot/42/pose/x").unwrap())
+            .is_none()
+    );
+    assert!(
+        robot_pose
+            .parse(keyexpr::new("robot/*/pose").unwrap())
+            .is_some()
+    );
+
+    let wild = KeFormat::new("robot/*/$id");
+    assert_eq!(
+        wild.parse(keyexpr::new("robot/a/b").unwrap())
+            .and_then(|parsed| parsed.get("id")),
+        Some("b")
+    );
+}
+
+#[test]
+fn format_placeholders() {
+    // A placeholder without a value
+    let ke: ZResult<OwnedKeyExpr<32>> = crate::keformat!(robot_pose, name = 42);
+    assert_eq!(ke.err(), Some(KeyexprError::UnboundDollar.into()));
+
+    // A placeholder used twice takes the same value, a value given twice is taken once
+    let ke: OwnedKeyExpr<32> = crate::keformat!(robot_link, id = 1, id = 2).unwrap();
+    assert_eq!(ke.as_str(), "robot/1/link/1");
+    assert!(robot_link.placeholders().eq(["id", "id"]));
+    assert_eq!(robot_link.parse(&ke).and_then(|p| p.get("id")), Some("1"));
+
+    // A value that isn't a valid chunk
+    let ke: ZResult<OwnedKeyExpr<32>> = crate::keformat!(robot_pose, id = "a/#");
+    assert!(ke.is_err());
+
+    // A value spanning several chunks, which `parse` couldn't match back
+    let ke: ZResult<OwnedKeyExpr<32>> = crate::keformat!(robot_pose, id = "a/b");
+    assert_eq!(ke.err(), Some(KeyexprError::SlashInChunk.into()));
+}
+
+#[test]
+fn format_capacity() {
+    let ke: ZResult<OwnedKeyExpr<8>> = crate::keformat!(robot_pose, id = 42);
+    assert_eq!(ke.err(), Some(CollectionError::CollectionTooSmall.into()));
+
+    // The last chunk doesn't fit
+    let ke: ZResult<OwnedKeyExpr<16>> = crate::keformat!(robot_pose, id = 123456789);
+    assert_eq!(ke.err(), Some(CollectionError::CollectionTooSmall.into()));
+}
+
+#[test]
+fn format_spec() {
+    for valid in [
+        "robot",
+        "robot/*/pose",
+        "robot/pose$*",
+        "robot/$*pose$*/x",
+        "$id",
+    ] {
+        assert_eq!(KeFormat::new(valid).as_str(), valid);
+    }
+
+    for invalid in [
+        "",
+        "robot/",
+        "/robot",
+        "robot//pose",
+        "robot/#",
+        "robot/pose?",
+        "robot/pose*",
+        "robot/*pose",
+        "robot/***",
+        "robot/**/pose",
+        "robot/$id/**",
+        "robot/*/**",
+        "robot/**/*",
+        "robot/**/**",
+        "robot/$*",
+        "robot/$",
+        "robot/x$id",
+        "robot/$id-x",
+        "robot/$id$*",
+    ] {
+        assert!(
+            catch_unwind(|| KeFormat::new(invalid)).is_err(),
+            "{invalid}"
+        );
+    }
+}
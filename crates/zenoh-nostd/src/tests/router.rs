@@ -0,0 +1,492 @@
+extern crate std;
+
+use core::cell::RefCell;
+use std::boxed::Box;
+
+use embassy_futures::select::{Either, select};
+use embassy_sync::{blocking_mutex::raw::NoopRawMutex, pipe::Pipe};
+use embassy_time::{Duration, Timer};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use zenoh_proto::{
+    BatchReader, BatchWriter, Message, ZFramed,
+    exts::{HasUsrPwd, QoS, UsrPwdNonce, UsrPwdProof},
+    fields::{Field, Reliability, Resolution},
+    msgs::{
+        Close, Declare, DeclareBody, DeclareFinal, InitAck, InitResolution, Interest,
+        InterestInner, InterestMode, InterestOptions, OpenAck,
+    },
+};
+
+use crate::{
+    Credentials, FixedCapacityCompletionCallbacks, FixedCapacityConsolidation,
+    FixedCapacityGetCallbacks, FixedCapacityInterests, FixedCapacityKeyExprs,
+    FixedCapacityMatchingCallbacks, FixedCapacityQueryableCallbacks, FixedCapacitySubCallbacks,
+    Identity, Resources, Session, ZConfig,
+    platform::{
+        ZPlatform,
+        tcp::{ZTcpRx, ZTcpStream, ZTcpTx},
+        udp::DummyUdpSocket,
+        ws::DummyWsStream,
+    },
+    storage::RawOrBox,
+};
+
+const BUFF_SIZE: usize = 2048;
+
+/// The nonce the router challenges a usrpwd authentication with.
+const USRPWD_NONCE: u64 = 0x5eed;
+
+type MockPipe = Pipe<NoopRawMutex, BUFF_SIZE>;
+
+/// One end of an in-memory TCP stream.
+pub(crate) struct MockStream {
+    tx: &'static MockPipe,
+    rx: &'static MockPipe,
+}
+
+pub(crate) struct MockTx(&'static MockPipe);
+pub(crate) struct MockRx(&'static MockPipe);
+
+async fn write(pipe: &MockPipe, buffer: &[u8]) -> core::result::Result<usize, crate::LinkError> {
+    Ok(pipe.write(buffer).await)
+}
+
+async fn write_all(pipe: &MockPipe, buffer: &[u8]) -> core::result::Result<(), crate::LinkError> {
+    pipe.write_all(buffer).await;
+    Ok(())
+}
+
+async fn read(pipe: &MockPipe, buffer: &mut [u8]) -> core::result::Result<usize, crate::LinkError> {
+    Ok(pipe.read(buffer).await)
+}
+
+async fn read_exact(
+    pipe: &MockPipe,
+    mut buffer: &mut [u8],
+) -> core::result::Result<(), crate::LinkError> {
+    while !buffer.is_empty() {
+        let n = pipe.read(buffer).await;
+        buffer = &mut buffer[n..];
+    }
+
+    Ok(())
+}
+
+impl ZTcpStream for MockStream {
+    type Tx<'a> = MockTx;
+    type Rx<'a> = MockRx;
+
+    fn split(&mut self) -> (Self::Tx<'_>, Self::Rx<'_>) {
+        (MockTx(self.tx), MockRx(self.rx))
+    }
+
+    fn mtu(&self) -> u16 {
+        BUFF_SIZE as u16
+    }
+}
+
+impl ZTcpTx for MockStream {
+    async fn write(&mut self, buffer: &[u8]) -> core::result::Result<usize, crate::LinkError> {
+        write(self.tx, buffer).await
+    }
+
+    async fn write_all(&mut self, buffer: &[u8]) -> core::result::Result<(), crate::LinkError> {
+        write_all(self.tx, buffer).await
+    }
+}
+
+impl ZTcpTx for MockTx {
+    async fn write(&mut self, buffer: &[u8]) -> core::result::Result<usize, crate::LinkError> {
+        write(self.0, buffer).await
+    }
+
+    async fn write_all(&mut self, buffer: &[u8]) -> core::result::Result<(), crate::LinkError> {
+        write_all(self.0, buffer).await
+    }
+}
+
+impl ZTcpRx for MockStream {
+    async fn read(&mut self, buffer: &mut [u8]) -> core::result::Result<usize, crate::LinkError> {
+        read(self.rx, buffer).await
+    }
+
+    async fn read_exact(
+        &mut self,
+        buffer: &mut [u8],
+    ) -> core::result::Result<(), crate::LinkError> {
+        read_exact(self.rx, buffer).await
+    }
+}
+
+impl ZTcpRx for MockRx {
+    async fn read(&mut self, buffer: &mut [u8]) -> core::result::Result<usize, crate::LinkError> {
+        read(self.0, buffer).await
+    }
+
+    async fn read_exact(
+        &mut self,
+        buffer: &mut [u8],
+    ) -> core::result::Result<(), crate::LinkError> {
+        read_exact(self.0, buffer).await
+    }
+}
+
+pub(crate) struct MockPlatform {
+    stream: RefCell<Option<MockStream>>,
+}
+
+impl ZPlatform for MockPlatform {
+    type TcpStream = MockStream;
+    type UdpSocket = DummyUdpSocket;
+    type WebSocket = DummyWsStream;
+
+    fn fill_random(&self, buf: &mut [u8]) -> core::result::Result<(), crate::SessionError> {
+        for (i, byte) in buf.iter_mut().enumerate() {
+            *byte = i as u8 + 1;
+        }
+
+        Ok(())
+    }
+
+    async fn new_tcp_stream(
+        &self,
+        _addr: &core::net::SocketAddr,
+    ) -> core::result::Result<Self::TcpStream, crate::ConnectionError> {
+        self.stream
+            .borrow_mut()
+            .take()
+            .ok_or(crate::ConnectionError::CouldNotConnect)
+    }
+}
+
+/// Small tables, so that tests can run out of slots.
+pub(crate) struct TestConfig {
+    platform: MockPlatform,
+    tx: [u8; BUFF_SIZE],
+    rx: [u8; BUFF_SIZE],
+
+    pub(crate) resolution: Resolution,
+    pub(crate) credentials: Option<Credentials>,
+    pub(crate) identity: Identity<'static>,
+    pub(crate) keep_alive: usize,
+}
+
+impl ZConfig for TestConfig {
+    type Platform = MockPlatform;
+
+    type GetCallbacks<'res> = FixedCapacityGetCallbacks<'res, 4, RawOrBox<32>, RawOrBox<256>>;
+    type SubCallbacks<'res> = FixedCapacitySubCallbacks<'res, 4, RawOrBox<32>, RawOrBox<256>>;
+    type QueryableCallbacks<'res> =
+        FixedCapacityQueryableCallbacks<'res, Self, 4, RawOrBox<32>, RawOrBox<1024>>;
+    type MatchingCallbacks<'res> = FixedCapacityMatchingCallbacks<'res, 4>;
+    type CompletionCallbacks<'res> = FixedCapacityCompletionCallbacks<'res, 4>;
+
+    type KeyExprs = FixedCapacityKeyExprs<2>;
+    type Interests = FixedCapacityInterests<2>;
+    type Consolidation = FixedCapacityConsolidation<4>;
+
+    type TxBuf = [u8; BUFF_SIZE];
+    type RxBuf = [u8; BUFF_SIZE];
+
+    fn platform(&self) -> &Self::Platform {
+        &self.platform
+    }
+
+    fn txrx(&mut self) -> (&mut Self::TxBuf, &mut Self::RxBuf) {
+        (&mut self.tx, &mut self.rx)
+    }
+
+    fn into_parts(self) -> (Self::Platform, Self::TxBuf, Self::RxBuf) {
+        (self.platform, self.tx, self.rx)
+    }
+
+    fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    fn credentials(&self) -> Option<Credentials> {
+        self.credentials
+    }
+
+    fn identity(&self) -> Identity<'_> {
+        self.identity
+    }
+
+    fn keep_alive(&self) -> usize {
+        self.keep_alive
+    }
+}
+
+/// The remote end of a `MockStream`, playing a zenoh router by hand.
+pub(crate) struct Router {
+    tx: &'static MockPipe,
+    rx: &'static MockPipe,
+    sn: u32,
+    buf: [u8; BUFF_SIZE],
+
+    /// The resolution negotiated during `accept`, the router takes whatever the session proposes.
+    pub(crate) resolution: Resolution,
+    /// The user and password the router accepts, if it asks for a usrpwd authentication.
+    pub(crate) credentials: Option<Credentials>,
+}
+
+impl Router {
+    pub(crate) fn new() -> (TestConfig, Self) {
+        let (up, down) = (
+            &*Box::leak(Box::new(MockPipe::new())),
+            &*Box::leak(Box::new(MockPipe::new())),
+        );
+
+        let config = TestConfig {
+            platform: MockPlatform {
+                stream: RefCell::new(Some(MockStream { tx: up, rx: down })),
+            },
+            tx: [0; BUFF_SIZE],
+            rx: [0; BUFF_SIZE],
+            resolution: Resolution::default(),
+            credentials: None,
+            identity: Identity::Random,
+            keep_alive: 4,
+        };
+
+        let router = Self {
+            tx: down,
+            rx: up,
+            sn: 0,
+            buf: [0; BUFF_SIZE],
+            resolution: Resolution::default(),
+            credentials: None,
+        };
+
+        (config, router)
+    }
+
+    /// Reads the next batch sent by the session.
+    pub(crate) async fn recv(&mut self) -> BatchReader<'_, &[u8]> {
+        let mut len = [0u8; 2];
+        read_exact(self.rx, &mut len).await.unwrap();
+
+        let len = u16::from_le_bytes(len) as usize;
+        read_exact(self.rx, &mut self.buf[..len]).await.unwrap();
+
+        BatchReader::new(&self.buf[..len])
+    }
+
+    /// Reads batches until a message matches `f`, skipping everything else.
+    pub(crate) async fn recv_until(&mut self, mut f: impl FnMut(&Message<'_>) -> bool) {
+        loop {
+            if self.recv().await.any(|msg| f(&msg)) {
+                return;
+            }
+        }
+    }
+
+    /// Writes raw bytes on the stream, possibly a fraction of a batch.
+    pub(crate) async fn send_raw(&mut self, bytes: &[u8]) {
+        self.tx.write_all(bytes).await;
+    }
+
+    /// Encodes `msg` in a batch prefixed by its length, returns the length of the whole.
+    pub(crate) fn batch(&mut self, msg: impl ZFramed, tx: &mut [u8]) -> usize {
+        let mut batch = BatchWriter::with_resolution(
+            &mut tx[2..],
+            self.sn,
+            self.resolution.get(Field::FrameSN),
+        );
+        batch
+            .framed(&msg, Reliability::Reliable, QoS::default())
+            .unwrap();
+        let (sn, len) = batch.finalize();
+        self.sn = sn;
+
+        tx[..2].copy_from_slice(&(len as u16).to_le_bytes());
+        len + 2
+    }
+
+    pub(crate) async fn send(&mut self, msg: impl ZFramed) {
+        let mut tx = [0u8; BUFF_SIZE];
+
+        let len = self.batch(msg, &mut tx);
+        self.send_raw(&tx[..len]).await;
+    }
+
+    /// Waits until the session has handled everything sent so far, by a round trip of a current
+    /// interest: the driver answers it with a `DeclareFinal` once the previous batches are done.
+    pub(crate) async fn sync(&mut self) {
+        self.sync_with(|_| {}).await
+    }
+
+    /// Same as `sync`, passing everything the session sent before its answer to `f`.
+    pub(crate) async fn sync_with(&mut self, mut f: impl FnMut(&Message<'_>)) {
+        const SYNC_ID: u32 = u32::MAX;
+
+        self.send(Interest {
+            id: SYNC_ID,
+            mode: InterestMode::Current,
+            inner: InterestInner {
+                options: InterestOptions::KEYEXPRS.options,
+                wire_expr: None,
+            },
+            ..Default::default()
+        })
+        .await;
+
+        let synced = self.recv_until(|msg| {
+            f(msg);
+
+            matches!(
+                msg,
+                Message::Declare {
+                    body: Declare {
+                        id: Some(SYNC_ID),
+                        body: DeclareBody::DeclareFinal(DeclareFinal {}),
+                        ..
+                    },
+                    ..
+                }
+            )
+        });
+
+        if let Either::First(_) = select(Timer::after(Duration::from_secs(1)), synced).await {
+            panic!("The session didn't answer the sync interest");
+        }
+    }
+
+    /// Answers the session handshake, closes the link if its usrpwd proof doesn't match.
+    async fn accept(&mut self) {
+        let mut tx = [0u8; BUFF_SIZE];
+        let mut auth = [0u8; 64];
+
+        let (resolution, usrpwd) = match self.recv().await.next() {
+            Some(Message::InitSyn(init_syn)) => (
+                init_syn.resolution.resolution,
+                init_syn
+                    .auth
+                    .as_ref()
+                    .and_then(HasUsrPwd::from_auth)
+                    .is_some(),
+            ),
+            _ => panic!("Expected an InitSyn"),
+        };
+        self.resolution = resolution;
+
+        let init_ack = InitAck {
+            resolution: InitResolution {
+                resolution: self.resolution,
+                ..Default::default()
+            },
+            auth: match usrpwd {
+                true => Some(
+                    UsrPwdNonce {
+                        nonce: USRPWD_NONCE,
+                    }
+                    .to_auth(&mut auth)
+                    .unwrap(),
+                ),
+                false => None,
+            },
+            ..Default::default()
+        };
+
+        let mut batch = BatchWriter::new(&mut tx[2..], 0);
+        batch.unframed(&init_ack).unwrap();
+        let (_, len) = batch.finalize();
+        tx[..2].copy_from_slice(&(len as u16).to_le_bytes());
+        self.send_raw(&tx[..len + 2]).await;
+
+        let credentials = self.credentials;
+        let authenticated = match self.recv().await.next() {
+            Some(Message::OpenSyn(open_syn)) => {
+                let proof = open_syn.auth.as_ref().and_then(UsrPwdProof::from_auth);
+
+                match (credentials, proof) {
+                    (None, _) => true,
+                    (Some(credentials), Some(proof)) => {
+                        proof.user == credentials.user.as_bytes()
+                            && proof.hmac == usrpwd_hmac(USRPWD_NONCE, credentials.password)
+                    }
+                    (Some(_), None) => false,
+                }
+            }
+            _ => panic!("Expected an OpenSyn"),
+        };
+
+        let mut batch = BatchWriter::new(&mut tx[2..], 0);
+        if authenticated {
+            let open_ack = OpenAck {
+                lease: core::time::Duration::from_secs(60),
+                auth: match usrpwd {
+                    true => Some(HasUsrPwd {}.to_auth(&mut auth).unwrap()),
+                    false => None,
+                },
+                ..Default::default()
+            };
+
+            batch.unframed(&open_ack).unwrap();
+        } else {
+            batch.unframed(&Close::default()).unwrap();
+        }
+        let (_, len) = batch.finalize();
+        tx[..2].copy_from_slice(&(len as u16).to_le_bytes());
+        self.send_raw(&tx[..len + 2]).await;
+    }
+}
+
+/// The usrpwd proof of `password`, computed independently from the transport.
+fn usrpwd_hmac(nonce: u64, password: &str) -> [u8; 32] {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&nonce.to_le_bytes()).unwrap();
+    Mac::update(&mut mac, password.as_bytes());
+    mac.finalize().into_bytes().into()
+}
+
+/// Opens a session against a `Router` set up by `configure`, and returns the outcome.
+pub(crate) fn open_with(
+    configure: impl FnOnce(&mut TestConfig, &mut Router),
+) -> crate::ZResult<()> {
+    embassy_futures::block_on(async {
+        let (mut config, mut router) = Router::new();
+        configure(&mut config, &mut router);
+        let mut resources = Resources::new();
+
+        let endpoint = crate::EndPoint::try_from("tcp/127.0.0.1:7447").unwrap();
+        let mut open = core::pin::pin!(crate::open(&mut resources, config, endpoint));
+
+        // The session may fail before the router has anything to accept
+        let session = match select(open.as_mut(), router.accept()).await {
+            Either::First(session) => session,
+            Either::Second(_) => open.await,
+        };
+
+        session.map(|_| ())
+    })
+}
+
+/// Opens a session against a `Router`, then runs `test` while the session is driven.
+pub(crate) fn run(test: impl AsyncFnOnce(&Session<'_, TestConfig>, &mut Router)) {
+    run_with(|_| {}, test)
+}
+
+/// Same as `run`, with a `TestConfig` adjusted by `configure` before opening the session.
+pub(crate) fn run_with(
+    configure: impl FnOnce(&mut TestConfig),
+    test: impl AsyncFnOnce(&Session<'_, TestConfig>, &mut Router),
+) {
+    embassy_futures::block_on(async {
+        let (mut config, mut router) = Router::new();
+        configure(&mut config);
+        let mut resources = Resources::new();
+
+        let endpoint = crate::EndPoint::try_from("tcp/127.0.0.1:7447").unwrap();
+        let (session, _) =
+            embassy_futures::join::join(crate::open(&mut resources, config, endpoint), async {
+                router.accept().await
+            })
+            .await;
+        let session = session.unwrap();
+
+        if let Either::First(res) = select(session.run(), test(&session, &mut router)).await {
+            panic!("The session stopped: {:?}", res.err());
+        }
+    });
+}
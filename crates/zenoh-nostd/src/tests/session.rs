@@ -0,0 +1,1484 @@
+use core::{
+    cell::Cell,
+    pin::pin,
+    sync::atomic::{AtomicUsize, Ordering},
+    task::{Context, Waker},
+};
+use embassy_futures::select::{Either, select};
+use embassy_sync::{blocking_mutex::raw::NoopRawMutex, channel::Channel};
+use embassy_time::{Duration, Timer};
+use std::{boxed::Box, sync::Arc, task::Wake};
+use zenoh_proto::{
+    BatchWriter, Message,
+    exts::{EntityGlobalId, QoS, SourceInfo},
+    fields::{Bits, NTP64, Reliability, Resolution, WireExpr, ZenohIdProto},
+    msgs::{
+        Declare, DeclareBody, DeclareFinal, DeclareKeyExpr, DeclareQueryable, DeclareSubscriber,
+        DeclareToken, Interest, InterestInner, InterestMode, InterestOptions, Push, PushBody, Put,
+        Query, Reply, Request, RequestBody, Response, ResponseBody, ResponseFinal,
+        UndeclareQueryable, UndeclareSubscriber, UndeclareToken,
+    },
+};
+
+use crate::{
+    ConsolidationMode, Credentials, EntityKind, Identity, MatchingStatus, QueryTarget, SampleKind,
+    ZConfig,
+    api::{callbacks::ZCallbacks, interests::ZInterests, keyexprs::ZKeyExprs},
+    keyexpr,
+    platform::{ZPlatform, tcp::DummyTcpStream, udp::DummyUdpSocket, ws::DummyWsStream},
+};
+
+use super::router::{Router, open_with, run, run_with};
+
+fn undeclared(msg: &Message<'_>) -> bool {
+    matches!(
+        msg,
+        Message::Declare { body, .. } if matches!(body.body, DeclareBody::UndeclareKeyExpr(_))
+    )
+}
+
+/// Waits for the next interest sent by the session and returns its id.
+async fn interest_id(router: &mut Router) -> u32 {
+    let mut id = None;
+    router
+        .recv_until(|msg| match msg {
+            Message::Interest {
+                body: Interest { id: i, .. },
+                ..
+            } => id.replace(*i).is_none(),
+            _ => false,
+        })
+        .await;
+
+    id.unwrap()
+}
+
+/// A remote declaration of `kind`, sent outside of any interest reply.
+fn declare(kind: EntityKind, id: u32, ke: &'static str) -> Declare<'static> {
+    let wire_expr = WireExpr::from(keyexpr::new(ke).unwrap());
+
+    Declare {
+        body: match kind {
+            EntityKind::Subscriber => {
+                DeclareBody::DeclareSubscriber(DeclareSubscriber { id, wire_expr })
+            }
+            EntityKind::Queryable => DeclareBody::DeclareQueryable(DeclareQueryable {
+                id,
+                wire_expr,
+                ..Default::default()
+            }),
+            EntityKind::Token => DeclareBody::DeclareToken(DeclareToken { id, wire_expr }),
+        },
+        ..Default::default()
+    }
+}
+
+fn undeclare(kind: EntityKind, id: u32) -> Declare<'static> {
+    Declare {
+        body: match kind {
+            EntityKind::Subscriber => DeclareBody::UndeclareSubscriber(UndeclareSubscriber {
+                id,
+                ..Default::default()
+            }),
+            EntityKind::Queryable => DeclareBody::UndeclareQueryable(UndeclareQueryable {
+                id,
+                ..Default::default()
+            }),
+            EntityKind::Token => DeclareBody::UndeclareToken(UndeclareToken {
+                id,
+                ..Default::default()
+            }),
+        },
+        ..Default::default()
+    }
+}
+
+/// Waits for the next request sent by the session and returns its target, budget, timeout and
+/// consolidation.
+async fn request_options(
+    router: &mut Router,
+) -> (
+    QueryTarget,
+    Option<u32>,
+    Option<core::time::Duration>,
+    ConsolidationMode,
+) {
+    let mut options = None;
+    router
+        .recv_until(|msg| match msg {
+            Message::Request {
+                body:
+                    Request {
+                        target,
+                        budget,
+                        timeout,
+                        payload: RequestBody::Query(Query { consolidation, .. }),
+                        ..
+                    },
+                ..
+            } => {
+                options = Some((
+                    *target,
+                    budget.as_ref().map(|b| b.budget),
+                    *timeout,
+                    *consolidation,
+                ));
+                true
+            }
+            _ => false,
+        })
+        .await;
+
+    options.unwrap()
+}
+
+fn interest_final(msg: &Message<'_>) -> bool {
+    matches!(msg, Message::InterestFinal { .. })
+}
+
+/// Counts the responses to `rid` until its `ResponseFinal`, which must come within a second.
+async fn replies(router: &mut Router, rid: u32) -> usize {
+    let mut count = 0;
+    let done = router.recv_until(|msg| match msg {
+        Message::Response {
+            body: Response { rid: r, .. },
+            ..
+        } if *r == rid => {
+            count += 1;
+            false
+        }
+        Message::ResponseFinal {
+            body: ResponseFinal { rid: r, .. },
+            ..
+        } => *r == rid,
+        _ => false,
+    });
+
+    if let Either::First(_) = select(Timer::after(Duration::from_secs(1)), done).await {
+        panic!("The session didn't finalize the request {}", rid);
+    }
+
+    count
+}
+
+#[test]
+fn undeclare_frees_slots() {
+    run(async |session, router| {
+        let (a, b, c, d) = (
+            keyexpr::new("test/a").unwrap(),
+            keyexpr::new("test/b").unwrap(),
+            keyexpr::new("test/c").unwrap(),
+            keyexpr::new("test/d").unwrap(),
+        );
+
+        // `TestConfig` has room for two key expressions and two interests
+        let publisher = session.declare_publisher(a).finish().await.unwrap();
+        let querier = session.declare_querier(b).finish().await.unwrap();
+
+        assert_eq!(
+            session.declare_publisher(c).finish().await.err(),
+            Some(crate::CollectionError::CollectionIsFull.into())
+        );
+
+        publisher.undeclare().await.unwrap();
+        router.recv_until(interest_final).await;
+        router.recv_until(undeclared).await;
+
+        let publisher = session.declare_publisher(c).finish().await.unwrap();
+
+        querier.undeclare().await.unwrap();
+        router.recv_until(interest_final).await;
+        router.recv_until(undeclared).await;
+
+        let querier = session.declare_querier(d).finish().await.unwrap();
+
+        publisher.undeclare().await.unwrap();
+        querier.undeclare().await.unwrap();
+    });
+}
+
+#[test]
+fn failed_declaration_released() {
+    run(async |session, router| {
+        let ke = keyexpr::new("test/**").unwrap();
+        let (a, b) = (
+            keyexpr::new("test/a").unwrap(),
+            keyexpr::new("test/b").unwrap(),
+        );
+
+        // Two pending liveliness gets fill the interests, not the key expressions
+        for _ in 0..2 {
+            session
+                .liveliness()
+                .get(ke)
+                .callback_sync(|_| {})
+                .finish()
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(
+            session.declare_publisher(a).finish().await.err(),
+            Some(crate::CollectionError::CollectionIsFull.into())
+        );
+        router.recv_until(undeclared).await;
+
+        assert_eq!(
+            session.declare_querier(b).finish().await.err(),
+            Some(crate::CollectionError::CollectionIsFull.into())
+        );
+        router.recv_until(undeclared).await;
+
+        let keyexprs = session.resources.keyexprs.lock().await;
+        assert!(keyexprs.find(a).is_none());
+        assert!(keyexprs.find(b).is_none());
+    });
+}
+
+#[test]
+fn keyexpr_ids_reused() {
+    run(async |session, _| {
+        let (a, b, c) = (
+            keyexpr::new("test/a").unwrap(),
+            keyexpr::new("test/b").unwrap(),
+            keyexpr::new("test/c").unwrap(),
+        );
+
+        let publisher = session.declare_publisher(a).finish().await.unwrap();
+        let _b = session.declare_querier(b).finish().await.unwrap();
+        publisher.undeclare().await.unwrap();
+
+        // The smallest free id is handed out, not the next one of a counter
+        let _c = session.declare_publisher(c).finish().await.unwrap();
+
+        let keyexprs = session.resources.keyexprs.lock().await;
+        assert_eq!(keyexprs.find(c), Some(1));
+        assert_eq!(keyexprs.find(b), Some(2));
+    });
+}
+
+#[test]
+fn remote_keyexprs_overflow() {
+    run(async |session, router| {
+        // `TestConfig` has room for two remote key expressions, the third one is dropped
+        for id in 1..=3 {
+            router
+                .send(Declare {
+                    body: DeclareBody::DeclareKeyExpr(DeclareKeyExpr {
+                        id,
+                        wire_expr: WireExpr::from(keyexpr::new("test/remote").unwrap()),
+                    }),
+                    ..Default::default()
+                })
+                .await;
+        }
+
+        router.sync().await;
+
+        let keyexprs = session.resources.remote_keyexprs.lock().await;
+        assert!(keyexprs.get(2).is_some());
+        assert!(keyexprs.get(3).is_none());
+    });
+}
+
+#[test]
+fn liveliness_get_final() {
+    run(async |session, router| {
+        let ke = keyexpr::new("test/**").unwrap();
+
+        session
+            .liveliness()
+            .get(ke)
+            .callback_sync(|_| {})
+            .finish()
+            .await
+            .unwrap();
+
+        let id = interest_id(router).await;
+
+        // A `DeclareFinal` that doesn't answer a liveliness get is ignored
+        router
+            .send(Declare {
+                id: Some(id + 1),
+                body: DeclareBody::DeclareFinal(DeclareFinal {}),
+                ..Default::default()
+            })
+            .await;
+        router.sync().await;
+
+        let mut gets = session.resources.liveliness_get_callbacks.lock().await;
+        assert!(gets.get(id).is_some());
+        drop(gets);
+
+        router
+            .send(Declare {
+                id: Some(id),
+                body: DeclareBody::DeclareFinal(DeclareFinal {}),
+                ..Default::default()
+            })
+            .await;
+        router.sync().await;
+
+        let mut gets = session.resources.liveliness_get_callbacks.lock().await;
+        assert!(gets.get(id).is_none());
+        assert!(session.resources.interests.lock().await.mode(id).is_none());
+    });
+}
+
+#[test]
+fn liveliness_get_timeout() {
+    run(async |session, router| {
+        let ke = keyexpr::new("test/**").unwrap();
+
+        session
+            .liveliness()
+            .get(ke)
+            .timeout(Duration::from_millis(10))
+            .callback_sync(|_| {})
+            .finish()
+            .await
+            .unwrap();
+
+        let id = interest_id(router).await;
+        assert!(session.resources.interests.lock().await.mode(id).is_some());
+
+        Timer::after(Duration::from_millis(50)).await;
+
+        let mut gets = session.resources.liveliness_get_callbacks.lock().await;
+        assert!(gets.get(id).is_none());
+        assert!(session.resources.interests.lock().await.mode(id).is_none());
+    });
+}
+
+#[test]
+fn failed_get_forgotten() {
+    run(async |session, _| {
+        let ke = keyexpr::new("test/**").unwrap();
+
+        for _ in 0..2 {
+            session
+                .liveliness()
+                .get(ke)
+                .callback_sync(|_| {})
+                .finish()
+                .await
+                .unwrap();
+        }
+
+        // The interests are full, so the third get can't be sent
+        assert!(
+            session
+                .liveliness()
+                .get(ke)
+                .callback_sync(|_| {})
+                .on_complete(async |_| {})
+                .finish()
+                .await
+                .is_err()
+        );
+
+        let gets = session.resources.liveliness_get_callbacks.lock().await;
+        assert_eq!(gets.keyexprs().count(), 2);
+        drop(gets);
+
+        let completion = session
+            .resources
+            .liveliness_completion_callbacks
+            .lock()
+            .await;
+        assert_eq!(completion.keyexprs().count(), 0);
+    });
+}
+
+#[test]
+fn liveliness_subscriber_undeclared() {
+    run(async |session, router| {
+        let ke = keyexpr::new("test/**").unwrap();
+
+        let subscriber = session
+            .liveliness()
+            .declare_subscriber(ke)
+            .callback_sync(|_| {})
+            .finish()
+            .await
+            .unwrap();
+        let id = interest_id(router).await;
+
+        subscriber.undeclare().await.unwrap();
+        router.recv_until(interest_final).await;
+
+        assert!(session.resources.interests.lock().await.mode(id).is_none());
+        let subs = session.resources.liveliness_callbacks.lock().await;
+        assert_eq!(subs.keyexprs().count(), 0);
+    });
+}
+
+#[test]
+fn liveliness_subscriber_tokens() {
+    run(async |session, router| {
+        let kind = &*Box::leak(Box::new(Cell::new(None)));
+
+        let _subscriber = session
+            .liveliness()
+            .declare_subscriber(keyexpr::new("test/**").unwrap())
+            .callback_sync(|sample| kind.set(Some(sample.kind())))
+            .finish()
+            .await
+            .unwrap();
+
+        router.send(declare(EntityKind::Token, 1, "test/a")).await;
+        router.sync().await;
+        assert_eq!(kind.take(), Some(SampleKind::Put));
+
+        router.send(undeclare(EntityKind::Token, 1)).await;
+        router.sync().await;
+        assert_eq!(kind.take(), Some(SampleKind::Delete));
+    });
+}
+
+#[test]
+fn failed_liveliness_subscriber_forgotten() {
+    run(async |session, _| {
+        let ke = keyexpr::new("test/**").unwrap();
+
+        for _ in 0..2 {
+            session
+                .liveliness()
+                .get(ke)
+                .callback_sync(|_| {})
+                .finish()
+                .await
+                .unwrap();
+        }
+
+        // The interests are full, so the subscriber can't be declared
+        assert_eq!(
+            session
+                .liveliness()
+                .declare_subscriber(ke)
+                .callback_sync(|_| {})
+                .finish()
+                .await
+                .err(),
+            Some(crate::CollectionError::CollectionIsFull.into())
+        );
+
+        let subs = session.resources.liveliness_callbacks.lock().await;
+        assert_eq!(subs.keyexprs().count(), 0);
+    });
+}
+
+#[test]
+fn get_completed_not_busy() {
+    struct Wakes(AtomicUsize);
+
+    impl Wake for Wakes {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    run(async |session, _| {
+        let wakes = Arc::new(Wakes(AtomicUsize::new(0)));
+        let waker = Waker::from(wakes.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let mut completed = pin!(session.resources.get_completed(0, false));
+
+        // A locked table doesn't wake the waiter right away, only the completion does
+        let gets = session.resources.get_callbacks.lock().await;
+        assert!(completed.as_mut().poll(&mut cx).is_pending());
+        drop(gets);
+        assert_eq!(wakes.0.load(Ordering::Relaxed), 0);
+
+        session
+            .resources
+            .complete_get(0, false, crate::CompletionReason::Timeout)
+            .await;
+        assert_eq!(wakes.0.load(Ordering::Relaxed), 1);
+        assert!(completed.as_mut().poll(&mut cx).is_ready());
+    });
+}
+
+#[test]
+fn partial_batch() {
+    run(async |session, router| {
+        let mut batch = [0u8; 128];
+        let len = router.batch(
+            Declare {
+                body: DeclareBody::DeclareKeyExpr(DeclareKeyExpr {
+                    id: 1,
+                    wire_expr: WireExpr::from(keyexpr::new("test/remote").unwrap()),
+                }),
+                ..Default::default()
+            },
+            &mut batch,
+        );
+
+        router.send_raw(&batch[..3]).await;
+        Timer::after(Duration::from_millis(10)).await;
+
+        // Wakes up the driver loop while the batch is half read
+        session
+            .get("test/**")
+            .unwrap()
+            .timeout(Duration::from_millis(10))
+            .callback_sync(|_| {})
+            .finish()
+            .await
+            .unwrap();
+        Timer::after(Duration::from_millis(50)).await;
+
+        router.send_raw(&batch[3..len]).await;
+        router.sync().await;
+
+        let keyexprs = session.resources.remote_keyexprs.lock().await;
+        assert!(keyexprs.get(1).is_some());
+    });
+}
+
+#[test]
+fn callback_queryable_finalized() {
+    run(async |session, router| {
+        let cache = &*Box::leak(Box::new(crate::AdvancedCache::<4>::new()));
+        let publisher = session
+            .declare_advanced_publisher(keyexpr::new("test/adv").unwrap(), cache)
+            .finish()
+            .await
+            .unwrap();
+
+        publisher.put(b"sample").finish().await.unwrap();
+
+        // More requests than the queryable table has counters, all of them must be released
+        for id in 1..=5 {
+            router
+                .send(Request {
+                    id,
+                    wire_expr: WireExpr::from(publisher.cache_keyexpr()),
+                    payload: RequestBody::Query(Query {
+                        parameters: "_anyke",
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+                .await;
+
+            assert_eq!(replies(router, id).await, 1);
+        }
+    });
+}
+
+#[test]
+fn advanced_publisher_undeclared() {
+    run(async |session, router| {
+        let ke = keyexpr::new("sensors/temperature/room1").unwrap();
+        let cache = &*Box::leak(Box::new(crate::AdvancedCache::<4>::new()));
+        let publisher = session
+            .declare_advanced_publisher(ke, cache)
+            .finish()
+            .await
+            .unwrap();
+
+        assert!(publisher.cache_keyexpr().as_str().starts_with(ke.as_str()));
+
+        publisher.undeclare().await.unwrap();
+        router
+            .recv_until(|msg| {
+                matches!(
+                    msg,
+                    Message::Declare { body, .. }
+                        if matches!(body.body, DeclareBody::UndeclareQueryable(_))
+                )
+            })
+            .await;
+        router.recv_until(undeclared).await;
+
+        // The publisher freed its key expression and interest
+        let _a = session.declare_publisher(ke).finish().await.unwrap();
+        let _b = session.declare_publisher(ke).finish().await.unwrap();
+    });
+}
+
+#[test]
+fn advanced_cache_reused() {
+    run(async |session, router| {
+        let ke = keyexpr::new("test/adv").unwrap();
+        let cache = &*Box::leak(Box::new(crate::AdvancedCache::<4>::new()));
+        fn query(id: u32, ke: &keyexpr) -> Request<'_> {
+            Request {
+                id,
+                wire_expr: WireExpr::from(ke),
+                payload: RequestBody::Query(Query {
+                    parameters: "_anyke",
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }
+        }
+
+        let publisher = session
+            .declare_advanced_publisher(ke, cache)
+            .finish()
+            .await
+            .unwrap();
+        publisher.put(b"sample").finish().await.unwrap();
+
+        // The cache serves one publisher at a time
+        assert_eq!(
+            session
+                .declare_advanced_publisher(ke, cache)
+                .finish()
+                .await
+                .err(),
+            Some(crate::CollectionError::KeyAlreadyExists.into())
+        );
+
+        publisher.undeclare().await.unwrap();
+
+        let publisher = session
+            .declare_advanced_publisher(ke, cache)
+            .finish()
+            .await
+            .unwrap();
+
+        // The samples of the previous publisher were dropped
+        router.send(query(1, publisher.cache_keyexpr())).await;
+        assert_eq!(replies(router, 1).await, 0);
+
+        publisher.put(b"sample").finish().await.unwrap();
+        router.send(query(2, publisher.cache_keyexpr())).await;
+        assert_eq!(replies(router, 2).await, 1);
+    });
+}
+
+#[test]
+fn failed_advanced_publisher_released() {
+    run(async |session, _| {
+        // `TestConfig` has room for four queryables
+        let mut queryables = std::vec::Vec::new();
+        for _ in 0..4 {
+            let queryable = session
+                .declare_queryable(keyexpr::new("test/queryable").unwrap())
+                .callback_sync(|_| {})
+                .finish()
+                .await
+                .unwrap();
+            queryables.push(queryable);
+        }
+
+        let ke = keyexpr::new("test/adv").unwrap();
+        let cache = &*Box::leak(Box::new(crate::AdvancedCache::<4>::new()));
+
+        // The cache isn't taken by a failed declaration
+        for _ in 0..3 {
+            assert_eq!(
+                session
+                    .declare_advanced_publisher(ke, cache)
+                    .finish()
+                    .await
+                    .err(),
+                Some(crate::CollectionError::CollectionIsFull.into())
+            );
+        }
+
+        // Neither are the key expressions and interests of the publishers
+        let _a = session.declare_publisher(ke).finish().await.unwrap();
+        let _b = session.declare_publisher(ke).finish().await.unwrap();
+    });
+}
+
+#[test]
+fn channel_queryable_finalized() {
+    run(async |session, router| {
+        let ke = keyexpr::new("test/channel").unwrap();
+        let channel = &*Box::leak(Box::new(Channel::<
+            NoopRawMutex,
+            crate::OwnedQuery<32, 32, 32>,
+            2,
+        >::new()));
+
+        let queryable = session
+            .declare_queryable(ke)
+            .channel(channel.dyn_sender(), channel.dyn_receiver())
+            .finish()
+            .await
+            .unwrap();
+
+        router
+            .send(Request {
+                id: 1,
+                wire_expr: WireExpr::from(ke),
+                payload: RequestBody::Query(Query::default()),
+                ..Default::default()
+            })
+            .await;
+
+        let query = queryable.recv().await.unwrap();
+        query.reply(ke, b"reply").finish().await.unwrap();
+
+        // The receiver sends the `ResponseFinal`, not the driver
+        query.finalize().await.unwrap();
+
+        assert_eq!(replies(router, 1).await, 1);
+    });
+}
+
+#[test]
+fn resolution_u64_downgraded() {
+    run_with(
+        |config| config.resolution = Resolution::new(Bits::U64, Bits::U64),
+        async |session, router| {
+            // SNs and request IDs are `u32`, a 64-bit resolution is proposed as 32 bits
+            let resolution = Resolution::new(Bits::U32, Bits::U32);
+            assert_eq!(router.resolution, resolution);
+            assert_eq!(session.resources.resolution, resolution);
+        },
+    );
+}
+
+#[test]
+fn resolution_u8_wraps() {
+    run_with(
+        |config| config.resolution = Resolution::new(Bits::U8, Bits::U8),
+        async |session, router| {
+            let mask = Bits::U8.mask();
+            let ke = keyexpr::new("test/wrap").unwrap();
+
+            let mut sns = std::vec::Vec::new();
+            for _ in 0..=mask {
+                session.put(ke, b"wrap").finish().await.unwrap();
+                router
+                    .recv_until(|msg| match msg {
+                        Message::Push { frame, .. } => {
+                            sns.push(frame.sn);
+                            true
+                        }
+                        _ => false,
+                    })
+                    .await;
+            }
+
+            assert!(sns.windows(2).all(|w| w[1] == (w[0] + 1) & mask));
+
+            let mut rid = session.resources.next_rid().await;
+            for _ in 0..=mask {
+                let next = session.resources.next_rid().await;
+                assert_eq!(next, (rid + 1) & mask);
+                rid = next;
+            }
+
+            // Frames from the router keep being accepted once their SN has wrapped
+            for _ in 0..=mask {
+                router.sync().await;
+            }
+
+            // A frame whose SN doesn't fit in the resolution is dropped
+            let mut tx = [0u8; 128];
+            let mut batch = BatchWriter::with_resolution(&mut tx[2..], mask + 1, Bits::U16);
+            batch
+                .framed(
+                    &Declare {
+                        body: DeclareBody::DeclareKeyExpr(DeclareKeyExpr {
+                            id: 1,
+                            wire_expr: WireExpr::from(keyexpr::new("test/remote").unwrap()),
+                        }),
+                        ..Default::default()
+                    },
+                    Reliability::Reliable,
+                    QoS::default(),
+                )
+                .unwrap();
+            let (_, len) = batch.finalize();
+            tx[..2].copy_from_slice(&(len as u16).to_le_bytes());
+
+            router.send_raw(&tx[..len + 2]).await;
+            router.sync().await;
+
+            assert!(
+                session
+                    .resources
+                    .remote_keyexprs
+                    .lock()
+                    .await
+                    .get(1)
+                    .is_none()
+            );
+        },
+    );
+}
+
+#[test]
+fn usrpwd_handshake() {
+    let credentials = Credentials {
+        user: "user",
+        password: "password",
+    };
+
+    assert!(
+        open_with(|config, router| {
+            config.credentials = Some(credentials);
+            router.credentials = Some(credentials);
+        })
+        .is_ok()
+    );
+
+    assert!(
+        open_with(|config, router| {
+            config.credentials = Some(Credentials {
+                password: "wrong",
+                ..credentials
+            });
+            router.credentials = Some(credentials);
+        })
+        .is_err()
+    );
+}
+
+#[test]
+fn derived_identity() {
+    let (config, _) = Router::new();
+    let platform = config.platform();
+
+    let seed = b"00:11:22:33:44:55";
+    let zid = Identity::Derived(seed).zid(platform).unwrap();
+
+    // The same seed always gives the same ZenohId, which is not the random one
+    assert_eq!(Identity::Derived(seed).zid(platform).unwrap(), zid);
+    assert_ne!(
+        Identity::Derived(b"00:11:22:33:44:56")
+            .zid(platform)
+            .unwrap(),
+        zid
+    );
+    assert_ne!(Identity::Random.zid(platform).unwrap(), zid);
+
+    run_with(
+        |config| config.identity = Identity::Derived(seed),
+        async |session, _| assert_eq!(session.resources.zid, zid),
+    );
+}
+
+#[test]
+fn default_random_identity() {
+    struct NoRng;
+
+    impl ZPlatform for NoRng {
+        type TcpStream = DummyTcpStream;
+        type UdpSocket = DummyUdpSocket;
+        type WebSocket = DummyWsStream;
+    }
+
+    // Platforms that don't provide `fill_random` still get random ZenohIds
+    let zid = Identity::Random.zid(&NoRng).unwrap();
+    assert_ne!(Identity::Random.zid(&NoRng).unwrap(), zid);
+}
+
+#[test]
+fn zero_keep_alive_rejected() {
+    assert_eq!(
+        open_with(|config, _| config.keep_alive = 0).err(),
+        Some(crate::SessionError::InvalidConfig.into())
+    );
+}
+
+#[test]
+fn matching_status() {
+    run(async |session, router| {
+        let ke = keyexpr::new("test/matching").unwrap();
+
+        let publisher = session.declare_publisher(ke).finish().await.unwrap();
+        assert!(!publisher.matching_status().await.matching());
+
+        router
+            .send(declare(EntityKind::Subscriber, 1, "test/**"))
+            .await;
+        router.sync().await;
+        assert!(publisher.matching_status().await.matching());
+
+        router.send(undeclare(EntityKind::Subscriber, 1)).await;
+        router.sync().await;
+        assert!(!publisher.matching_status().await.matching());
+
+        // Once no interest covers it, the router will never undeclare this subscriber
+        router
+            .send(declare(EntityKind::Subscriber, 2, "test/matching"))
+            .await;
+        router.sync().await;
+        publisher.undeclare().await.unwrap();
+
+        let interests = session.resources.interests.lock().await;
+        assert!(interests.declared(EntityKind::Subscriber, 2).is_none());
+        drop(interests);
+
+        let publisher = session.declare_publisher(ke).finish().await.unwrap();
+        assert!(!publisher.matching_status().await.matching());
+    });
+}
+
+#[test]
+fn matching_listener() {
+    run(async |session, router| {
+        let status = &*Box::leak(Box::new(Cell::new(None)));
+
+        let querier = session
+            .declare_querier(keyexpr::new("test/listener").unwrap())
+            .finish()
+            .await
+            .unwrap();
+        querier
+            .matching_listener(async |s: &MatchingStatus| status.set(Some(s.matching())))
+            .await
+            .unwrap();
+
+        router
+            .send(declare(EntityKind::Queryable, 1, "test/listener"))
+            .await;
+        router.sync().await;
+        assert_eq!(status.take(), Some(true));
+
+        // Only the transitions between matching and not matching are notified
+        router
+            .send(declare(EntityKind::Queryable, 2, "test/*"))
+            .await;
+        router.send(undeclare(EntityKind::Queryable, 1)).await;
+        router.sync().await;
+        assert_eq!(status.take(), None);
+
+        router.send(undeclare(EntityKind::Queryable, 2)).await;
+        router.sync().await;
+        assert_eq!(status.take(), Some(false));
+    });
+}
+
+#[test]
+fn publisher_matching_listener() {
+    run(async |session, router| {
+        let status = &*Box::leak(Box::new(Cell::new(None)));
+
+        // The listener doesn't need the key expression to outlive the session
+        let ke = std::string::String::from("test/listener");
+        let publisher = session
+            .declare_publisher(keyexpr::new(ke.as_str()).unwrap())
+            .finish()
+            .await
+            .unwrap();
+        publisher
+            .matching_listener(async |s: &MatchingStatus| status.set(Some(s.matching())))
+            .await
+            .unwrap();
+
+        router
+            .send(declare(EntityKind::Subscriber, 1, "test/listener"))
+            .await;
+        router.sync().await;
+        assert_eq!(status.take(), Some(true));
+
+        router.send(undeclare(EntityKind::Subscriber, 1)).await;
+        router.sync().await;
+        assert_eq!(status.take(), Some(false));
+    });
+}
+
+#[test]
+fn matching_listeners_undeclared() {
+    run(async |session, router| {
+        let (first, second, other) = (
+            &*Box::leak(Box::new(Cell::new(None))),
+            &*Box::leak(Box::new(Cell::new(None))),
+            &*Box::leak(Box::new(Cell::new(None))),
+        );
+
+        let querier = session
+            .declare_querier(keyexpr::new("test/listener").unwrap())
+            .finish()
+            .await
+            .unwrap();
+        let publisher = session
+            .declare_publisher(keyexpr::new("test/listener").unwrap())
+            .finish()
+            .await
+            .unwrap();
+
+        let listener = querier
+            .matching_listener(async |s: &MatchingStatus| first.set(Some(s.matching())))
+            .await
+            .unwrap();
+        let _second = querier
+            .matching_listener(async |s: &MatchingStatus| second.set(Some(s.matching())))
+            .await
+            .unwrap();
+        let _other = publisher
+            .matching_listener(async |s: &MatchingStatus| other.set(Some(s.matching())))
+            .await
+            .unwrap();
+
+        router
+            .send(declare(EntityKind::Queryable, 1, "test/listener"))
+            .await;
+        router.sync().await;
+        assert_eq!(first.take(), Some(true));
+        assert_eq!(second.take(), Some(true));
+
+        // Only the listeners of the querier are notified of a queryable
+        assert_eq!(other.take(), None);
+
+        listener.undeclare().await.unwrap();
+
+        router.send(undeclare(EntityKind::Queryable, 1)).await;
+        router.sync().await;
+        assert_eq!(first.take(), None);
+        assert_eq!(second.take(), Some(false));
+    });
+}
+
+#[test]
+fn write_filter_overflow() {
+    run(async |session, router| {
+        let publisher = session
+            .declare_publisher(keyexpr::new("test/filter").unwrap())
+            .write_filter(true)
+            .finish()
+            .await
+            .unwrap();
+
+        let id = interest_id(router).await;
+        router
+            .send(Declare {
+                id: Some(id),
+                body: DeclareBody::DeclareFinal(DeclareFinal {}),
+                ..Default::default()
+            })
+            .await;
+
+        // `TestConfig` has room for two remote declarations
+        router
+            .send(declare(EntityKind::Subscriber, 1, "other/a"))
+            .await;
+        router
+            .send(declare(EntityKind::Subscriber, 2, "other/b"))
+            .await;
+        router.sync().await;
+
+        let mut pushes = 0;
+        let mut count = |msg: &Message<'_>| pushes += matches!(msg, Message::Push { .. }) as usize;
+
+        publisher.put(b"filtered").finish().await.unwrap();
+        router.sync_with(&mut count).await;
+
+        // A matching subscriber that doesn't fit must not make the filter drop puts
+        router
+            .send(declare(EntityKind::Subscriber, 3, "test/filter"))
+            .await;
+        router.sync().await;
+        assert!(publisher.matching_status().await.matching());
+
+        publisher.put(b"sent").finish().await.unwrap();
+        router.sync_with(&mut count).await;
+
+        assert_eq!(pushes, 1);
+    });
+}
+
+#[test]
+fn publisher_source_info() {
+    run(async |session, router| {
+        let publisher = session
+            .declare_publisher(keyexpr::new("test/sinfo").unwrap())
+            .write_filter(true)
+            .source_info(true)
+            .finish()
+            .await
+            .unwrap();
+
+        let id = interest_id(router).await;
+        router
+            .send(Declare {
+                id: Some(id),
+                body: DeclareBody::DeclareFinal(DeclareFinal {}),
+                ..Default::default()
+            })
+            .await;
+        router.sync().await;
+
+        // A filtered put doesn't take a sequence number
+        publisher.put(b"filtered").finish().await.unwrap();
+
+        router
+            .send(declare(EntityKind::Subscriber, 1, "test/sinfo"))
+            .await;
+        router.sync().await;
+
+        publisher.put(b"first").finish().await.unwrap();
+        publisher.put(b"second").finish().await.unwrap();
+
+        // Puts only carry a source info when asked
+        let plain = session
+            .declare_publisher(keyexpr::new("test/sinfo").unwrap())
+            .finish()
+            .await
+            .unwrap();
+        plain.put(b"plain").finish().await.unwrap();
+
+        let mut sinfos = std::vec::Vec::new();
+        router
+            .sync_with(|msg| {
+                if let Message::Push {
+                    body:
+                        Push {
+                            payload: PushBody::Put(Put { sinfo, .. }),
+                            ..
+                        },
+                    ..
+                } = msg
+                {
+                    sinfos.push(sinfo.clone());
+                }
+            })
+            .await;
+
+        let sinfo = |sn| {
+            Some(SourceInfo {
+                id: publisher.id(),
+                sn,
+            })
+        };
+        assert_eq!(sinfos, [sinfo(0), sinfo(1), None]);
+    });
+}
+
+#[test]
+fn sample_source_info() {
+    run(async |session, router| {
+        let received = &*Box::leak(Box::new(Cell::new(None)));
+        let _sub = session
+            .declare_subscriber(keyexpr::new("test/sinfo").unwrap())
+            .callback_sync(|sample| received.set(Some(sample.source_info().cloned())))
+            .finish()
+            .await
+            .unwrap();
+
+        let sinfo = SourceInfo {
+            id: EntityGlobalId {
+                zid: ZenohIdProto::default(),
+                eid: 7,
+            },
+            sn: 42,
+        };
+
+        router
+            .send(Push {
+                wire_expr: WireExpr::from(keyexpr::new("test/sinfo").unwrap()),
+                payload: PushBody::Put(Put {
+                    sinfo: Some(sinfo.clone()),
+                    payload: b"sample",
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .await;
+        router.sync().await;
+
+        assert_eq!(received.take(), Some(Some(sinfo)));
+    });
+}
+
+#[test]
+fn reply_respid() {
+    run(async |session, router| {
+        let ke = keyexpr::new("test/respid").unwrap();
+        let queryable = session
+            .declare_queryable(ke)
+            .callback(async |query| {
+                let _ = query.reply(query.keyexpr(), b"reply").finish().await;
+            })
+            .finish()
+            .await
+            .unwrap();
+
+        router
+            .send(Request {
+                id: 1,
+                wire_expr: WireExpr::from(ke),
+                payload: RequestBody::Query(Query::default()),
+                ..Default::default()
+            })
+            .await;
+
+        let mut respid = None;
+        router
+            .recv_until(|msg| match msg {
+                Message::Response {
+                    body:
+                        Response {
+                            respid: r,
+                            payload: ResponseBody::Reply(Reply { .. }),
+                            ..
+                        },
+                    ..
+                } => {
+                    respid = r.clone();
+                    true
+                }
+                _ => false,
+            })
+            .await;
+
+        assert_eq!(respid, Some(queryable.id()));
+    });
+}
+
+#[test]
+fn interest_answered() {
+    run(async |session, router| {
+        let _sub = session
+            .declare_subscriber(keyexpr::new("test/sub").unwrap())
+            .callback_sync(|_| {})
+            .finish()
+            .await
+            .unwrap();
+        let _other = session
+            .declare_subscriber(keyexpr::new("other/sub").unwrap())
+            .callback_sync(|_| {})
+            .finish()
+            .await
+            .unwrap();
+        let _queryable = session
+            .declare_queryable(keyexpr::new("test/queryable").unwrap())
+            .callback_sync(|_| {})
+            .finish()
+            .await
+            .unwrap();
+
+        let interest = |id, mode| Interest {
+            id,
+            mode,
+            inner: InterestInner {
+                options: InterestOptions::SUBSCRIBERS.options | InterestOptions::QUERYABLES.options,
+                wire_expr: Some(WireExpr::from(keyexpr::new("test/**").unwrap())),
+            },
+            ..Default::default()
+        };
+
+        // A current interest is answered with the matching declarations and a `DeclareFinal`
+        router.send(interest(1, InterestMode::Current)).await;
+
+        let mut declared = std::vec::Vec::new();
+        router
+            .recv_until(|msg| match msg {
+                Message::Declare {
+                    body:
+                        Declare {
+                            id: Some(1), body, ..
+                        },
+                    ..
+                } => match body {
+                    DeclareBody::DeclareSubscriber(DeclareSubscriber { wire_expr, .. }) => {
+                        declared.push(std::format!("subscriber {}", wire_expr.suffix));
+                        false
+                    }
+                    DeclareBody::DeclareQueryable(DeclareQueryable { wire_expr, .. }) => {
+                        declared.push(std::format!("queryable {}", wire_expr.suffix));
+                        false
+                    }
+                    DeclareBody::DeclareFinal(_) => true,
+                    _ => false,
+                },
+                _ => false,
+            })
+            .await;
+
+        assert_eq!(
+            declared,
+            ["subscriber test/sub", "queryable test/queryable"]
+        );
+
+        // A future interest isn't answered, the declarations are sent as they happen
+        router.send(interest(2, InterestMode::Future)).await;
+
+        let mut answers = 0;
+        router
+            .sync_with(|msg| {
+                if let Message::Declare {
+                    body: Declare { id: Some(2), .. },
+                    ..
+                } = msg
+                {
+                    answers += 1;
+                }
+            })
+            .await;
+
+        assert_eq!(answers, 0);
+    });
+}
+
+#[test]
+fn get_options_on_the_wire() {
+    run(async |session, router| {
+        let ke = keyexpr::new("test/options").unwrap();
+
+        session
+            .get(ke)
+            .unwrap()
+            .target(QueryTarget::All)
+            .consolidation(ConsolidationMode::Monotonic)
+            .budget(3)
+            .timeout(Duration::from_millis(500))
+            .callback_sync(|_| {})
+            .finish()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            request_options(router).await,
+            (
+                QueryTarget::All,
+                Some(3),
+                Some(core::time::Duration::from_millis(500)),
+                ConsolidationMode::Monotonic
+            )
+        );
+
+        // Without options, the defaults are sent
+        session
+            .get(ke)
+            .unwrap()
+            .callback_sync(|_| {})
+            .finish()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            request_options(router).await,
+            (
+                QueryTarget::BestMatching,
+                None,
+                None,
+                ConsolidationMode::None
+            )
+        );
+
+        let querier = session
+            .declare_querier(ke)
+            .target(QueryTarget::AllComplete)
+            .consolidation(ConsolidationMode::Latest)
+            .budget(1)
+            .timeout(Duration::from_secs(2))
+            .finish()
+            .await
+            .unwrap();
+        querier.get().callback_sync(|_| {}).finish().await.unwrap();
+
+        // `Latest` is downgraded
+        assert_eq!(
+            request_options(router).await,
+            (
+                QueryTarget::AllComplete,
+                Some(1),
+                Some(core::time::Duration::from_secs(2)),
+                ConsolidationMode::Monotonic
+            )
+        );
+    });
+}
+
+#[test]
+fn invalid_selector() {
+    run(async |session, _| {
+        assert!(session.get("test//a").is_err());
+        assert!(session.get("test/a#?x=1").is_err());
+        assert!(session.get("test/a?x=1").is_ok());
+    });
+}
+
+#[test]
+fn auto_consolidation_resolved() {
+    run(async |session, router| {
+        session
+            .get("test/auto")
+            .unwrap()
+            .consolidation(ConsolidationMode::Auto)
+            .callback_sync(|_| {})
+            .finish()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            request_options(router).await.3,
+            ConsolidationMode::Monotonic
+        );
+
+        // The history asked by a time range isn't consolidated
+        session
+            .get("test/auto?_time=[now(-1h)..]")
+            .unwrap()
+            .consolidation(ConsolidationMode::Auto)
+            .callback_sync(|_| {})
+            .finish()
+            .await
+            .unwrap();
+
+        assert_eq!(request_options(router).await.3, ConsolidationMode::None);
+    });
+}
+
+#[test]
+fn latest_consolidation_downgraded() {
+    run(async |session, router| {
+        let ke = keyexpr::new("test/latest").unwrap();
+        let delivered = &*Box::leak(Box::new(Cell::new(0)));
+
+        session
+            .get(ke)
+            .unwrap()
+            .consolidation(ConsolidationMode::Latest)
+            .callback_sync(|_| delivered.set(delivered.get() + 1))
+            .finish()
+            .await
+            .unwrap();
+
+        let mut rid = None;
+        router
+            .recv_until(|msg| match msg {
+                Message::Request {
+                    body:
+                        Request {
+                            id,
+                            payload: RequestBody::Query(Query { consolidation, .. }),
+                            ..
+                        },
+                    ..
+                } => {
+                    assert_eq!(*consolidation, ConsolidationMode::Monotonic);
+                    rid.replace(*id).is_none()
+                }
+                _ => false,
+            })
+            .await;
+
+        let rid = rid.unwrap();
+        let zid = ZenohIdProto::default();
+
+        // The older reply arrives first, so both are delivered, unlike the last one
+        for time in [5, 10, 7] {
+            router
+                .send(Response {
+                    rid,
+                    wire_expr: WireExpr::from(ke),
+                    payload: ResponseBody::Reply(Reply {
+                        payload: PushBody::Put(Put {
+                            timestamp: Some(zid.timestamp(NTP64(time))),
+                            payload: b"reply",
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+                .await;
+        }
+
+        router.sync().await;
+        assert_eq!(delivered.get(), 2);
+    });
+}
@@ -43,6 +43,7 @@ pub struct BatchReader<'a, T> {
     reader: T,
     _lt: core::marker::PhantomData<&'a ()>,
     frame: Option<FrameHeader>,
+    sn_mask: u32,
 }
 
 impl<'a, T> BatchReader<'a, T>
@@ -54,8 +55,23 @@ where
             reader,
             _lt: core::marker::PhantomData,
             frame: None,
+            sn_mask: u32::MAX,
         }
     }
+
+    /// Frames whose SN doesn't fit in the negotiated `resolution` are rejected.
+    pub fn resolution(mut self, resolution: Bits) -> Self {
+        self.sn_mask = resolution.mask();
+        self
+    }
+
+    /// In low-latency mode network messages are not preceded by a `FrameHeader`.
+    pub fn lowlatency(mut self, lowlatency: bool) -> Self {
+        if lowlatency {
+            self.frame = Some(FrameHeader::default());
+        }
+        self
+    }
 }
 
 impl<'a, T> Iterator for BatchReader<'a, T>
@@ -106,6 +122,16 @@ where
 
             FrameHeader::ID => {
                 let frame = decode!(FrameHeader);
+                if frame.sn > self.sn_mask {
+                    crate::error!(
+                        "Frame SN {} exceeds the negotiated resolution. Skipping the rest of the message - {}",
+                        frame.sn,
+                        crate::zctx!()
+                    );
+
+                    return None;
+                }
+
                 self.frame = Some(frame);
                 return self.next();
             }
@@ -171,6 +197,8 @@ pub struct BatchWriter<'a, T> {
     _lt: core::marker::PhantomData<&'a ()>,
     frame: Option<FrameHeader>,
     sn: u32,
+    sn_mask: u32,
+    lowlatency: bool,
 
     init: usize,
 }
@@ -180,16 +208,29 @@ where
     T: crate::ZWriteable,
 {
     pub fn new(writer: T, sn: u32) -> Self {
+        Self::with_resolution(writer, sn, Bits::U32)
+    }
+
+    pub fn with_resolution(writer: T, sn: u32, resolution: Bits) -> Self {
         let init = writer.remaining();
+        let sn_mask = resolution.mask();
         Self {
             writer,
             _lt: core::marker::PhantomData,
             frame: None,
-            sn,
+            sn: sn & sn_mask,
+            sn_mask,
+            lowlatency: false,
             init,
         }
     }
 
+    /// In low-latency mode network messages are written without a `FrameHeader`.
+    pub fn lowlatency(mut self, lowlatency: bool) -> Self {
+        self.lowlatency = lowlatency;
+        self
+    }
+
     pub fn has_written(&self) -> bool {
         self.init != self.writer.remaining()
     }
@@ -226,6 +267,7 @@ impl ZFramed for Request<'_> {}
 impl ZFramed for Response<'_> {}
 impl ZFramed for ResponseFinal {}
 impl ZFramed for Interest<'_> {}
+impl ZFramed for InterestFinal {}
 impl ZFramed for Declare<'_> {}
 
 impl<'a, W> BatchWriter<'a, W>
@@ -238,7 +280,7 @@ where
         r: Reliability,
         qos: QoS,
     ) -> core::result::Result<(), crate::CodecError> {
-        if self.frame.as_ref().map(|f| f.reliability) != Some(r) {
+        if !self.lowlatency && self.frame.as_ref().map(|f| f.reliability) != Some(r) {
             <_ as ZEncode>::z_encode(
                 &FrameHeader {
                     reliability: r,
@@ -254,7 +296,7 @@ where
                 qos,
             });
 
-            self.sn += 1;
+            self.sn = self.sn.wrapping_add(1) & self.sn_mask;
         }
 
         <_ as ZEncode>::z_encode(x, &mut self.writer)?;
@@ -106,6 +106,54 @@ impl keyexpr {
         self.0.contains(SINGLE_WILD as char)
     }
 
+    /// Returns `true` if `self` contains a `*`, `**` or `$*` wildcard.
+    pub fn is_wild(&self) -> bool {
+        self.is_wild_impl()
+    }
+
+    /// Iterates over the `/`-separated chunks of `self`.
+    pub fn chunks(&self) -> impl DoubleEndedIterator<Item = &keyexpr> {
+        self.0
+            .split(DELIMITER as char)
+            .map(keyexpr::from_str_unchecked)
+    }
+
+    /// Writes the canonical form of `v` into `buf`: `$*` chunks become `*`, `$*$*` becomes `$*`,
+    /// and runs of `*` and `**` become their `*`s followed by a single `**`.
+    pub fn canonize<'b>(
+        v: &str,
+        buf: &'b mut [u8],
+    ) -> core::result::Result<&'b Self, crate::KeyexprError> {
+        let mut writer = ChunkWriter {
+            buf,
+            len: 0,
+            chunks: 0,
+        };
+        let mut chunks = v.split(DELIMITER as char).peekable();
+
+        while let Some(chunk) = chunks.next() {
+            if chunk == "**" {
+                let mut stars = 0;
+                while let Some(next) = chunks.next_if(|next| is_star(next) || *next == "**") {
+                    stars += is_star(next) as usize;
+                }
+
+                for _ in 0..stars {
+                    writer.push(b"*")?;
+                }
+                writer.push(b"**")?;
+            } else if is_star(chunk) {
+                writer.push(b"*")?;
+            } else {
+                writer.push(chunk.as_bytes())?;
+            }
+        }
+
+        let ChunkWriter { buf, len, .. } = writer;
+        // Only whole UTF-8 chunks and ASCII were copied
+        keyexpr::new(unsafe { core::str::from_utf8_unchecked(&buf[..len]) })
+    }
+
     pub const fn as_str(&self) -> &str {
         &self.0
     }
@@ -188,6 +236,58 @@ impl<'a> TryFrom<&'a keyexpr> for &'a nonwild_keyexpr {
     }
 }
 
+fn is_star(chunk: &str) -> bool {
+    chunk == "*"
+        || (!chunk.is_empty()
+            && chunk.len().is_multiple_of(2)
+            && chunk.as_bytes().chunks(2).all(|c| c == b"$*"))
+}
+
+struct ChunkWriter<'b> {
+    buf: &'b mut [u8],
+    len: usize,
+    chunks: usize,
+}
+
+impl ChunkWriter<'_> {
+    fn byte(&mut self, byte: u8) -> core::result::Result<(), crate::KeyexprError> {
+        let Some(dst) = self.buf.get_mut(self.len) else {
+            return Err(crate::KeyexprError::DstIsTooSmall);
+        };
+
+        *dst = byte;
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Appends `chunk` behind a `/`, collapsing repeated `$*`.
+    fn push(&mut self, chunk: &[u8]) -> core::result::Result<(), crate::KeyexprError> {
+        if self.chunks > 0 {
+            self.byte(DELIMITER)?;
+        }
+        self.chunks += 1;
+
+        let mut after_dollar_star = false;
+        let mut i = 0;
+        while i < chunk.len() {
+            if chunk[i..].starts_with(b"$*") {
+                if !after_dollar_star {
+                    self.byte(b'$')?;
+                    self.byte(b'*')?;
+                }
+                after_dollar_star = true;
+                i += 2;
+            } else {
+                self.byte(chunk[i])?;
+                after_dollar_star = false;
+                i += 1;
+            }
+        }
+
+        Ok(())
+    }
+}
+
 #[repr(u8)]
 enum MatchComplexity {
     NoWilds = 0,
@@ -226,6 +326,135 @@ impl keyexpr {
             _ => it_intersect::<true>(left, right),
         }
     }
+
+    /// Returns `true` if every key matched by `other` is also matched by `self`.
+    pub fn includes(&self, other: &Self) -> bool {
+        let left = self.as_bytes();
+        let right = other.as_bytes();
+
+        if left == right {
+            return true;
+        }
+
+        it_includes(left, right)
+    }
+
+    pub fn relation_to(&self, other: &Self) -> SetIntersectionLevel {
+        if self.as_bytes() == other.as_bytes() {
+            SetIntersectionLevel::Equals
+        } else if self.includes(other) {
+            SetIntersectionLevel::Includes
+        } else if self.intersects(other) {
+            SetIntersectionLevel::Intersects
+        } else {
+            SetIntersectionLevel::Disjoint
+        }
+    }
+}
+
+/// How the set of keys matched by a key expression relates to another one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SetIntersectionLevel {
+    Disjoint,
+    Intersects,
+    Includes,
+    Equals,
+}
+
+fn it_includes(mut left: &[u8], mut right: &[u8]) -> bool {
+    loop {
+        let (lchunk, lrest) = next(left);
+        let lempty = lrest.is_empty();
+
+        if lchunk == b"**" {
+            if lempty {
+                return !has_verbatim(right);
+            }
+
+            if it_includes(lrest, right) {
+                return true;
+            }
+
+            if has_direct_verbatim(right) {
+                return false;
+            }
+
+            right = match next(right).1 {
+                [] => return false,
+                rest => rest,
+            };
+        } else {
+            let (rchunk, rrest) = next(right);
+            if rchunk.is_empty() || rchunk == b"**" || !chunk_includes(lchunk, rchunk) {
+                return false;
+            }
+
+            let rempty = rrest.is_empty();
+            if lempty {
+                return rempty;
+            }
+
+            left = lrest;
+            if rempty {
+                return left == b"**";
+            }
+
+            right = rrest;
+        }
+    }
+}
+
+fn chunk_includes(lchunk: &[u8], rchunk: &[u8]) -> bool {
+    if lchunk == rchunk {
+        return true;
+    }
+
+    if has_direct_verbatim(lchunk) || has_direct_verbatim(rchunk) {
+        return false;
+    }
+
+    if lchunk == b"*" {
+        return true;
+    }
+
+    if !lchunk.contains(&b'$') {
+        return false;
+    }
+
+    // Every `$*` in `lchunk` may stand for any substring of `rchunk`, including its own `$*`
+    let mut needles = lchunk.split(|c| *c == b'$').map(|needle| match needle {
+        [b'*', rest @ ..] => rest,
+        needle => needle,
+    });
+
+    let Some(prefix) = needles.next() else {
+        return false;
+    };
+
+    let Some(mut rchunk) = rchunk.strip_prefix(prefix) else {
+        return false;
+    };
+
+    let Some(suffix) = needles.next_back() else {
+        return true;
+    };
+
+    let Some(stripped) = rchunk.strip_suffix(suffix) else {
+        return false;
+    };
+    rchunk = stripped;
+
+    for needle in needles {
+        match rchunk
+            .windows(needle.len())
+            .position(|window| window == needle)
+        {
+            Some(position) => rchunk = &rchunk[position + needle.len()..],
+            None => return false,
+        }
+    }
+
+    true
 }
 
 #[cold]
@@ -19,6 +19,9 @@ pub(crate) use codec::*;
 mod ke;
 pub use ke::*;
 
+mod selector;
+pub use selector::*;
+
 pub mod msgs;
 pub use msgs::{exts, fields};
 
@@ -2,7 +2,7 @@ use core::time::Duration;
 
 use crate::{fields::*, *};
 
-#[derive(ZExt, Debug, PartialEq, Default)]
+#[derive(ZExt, Debug, PartialEq, Default, Clone)]
 #[zenoh(header = "ID:4|_:4")]
 pub struct EntityGlobalId {
     #[zenoh(size = header(ID))]
@@ -11,7 +11,7 @@ pub struct EntityGlobalId {
     pub eid: u32,
 }
 
-#[derive(ZExt, Debug, PartialEq, Default)]
+#[derive(ZExt, Debug, PartialEq, Default, Clone)]
 pub struct SourceInfo {
     pub id: EntityGlobalId,
     pub sn: u32,
@@ -264,3 +264,97 @@ impl Patch {
         Self { int: 1 }
     }
 }
+
+/// Identifier of the user/password authentication inside the `Auth` extension.
+pub const AUTH_USRPWD_ID: u8 = 0x2;
+
+#[derive(ZExt, Debug, PartialEq, Default)]
+pub struct HasUsrPwd {}
+
+#[derive(ZExt, Debug, PartialEq, Default)]
+pub struct UsrPwdNonce {
+    pub nonce: u64,
+}
+
+#[derive(ZExt, Debug, PartialEq, Default)]
+pub struct UsrPwdProof<'a> {
+    #[zenoh(size = prefixed)]
+    pub user: &'a [u8],
+    #[zenoh(size = prefixed)]
+    pub hmac: &'a [u8],
+}
+
+impl HasUsrPwd {
+    pub fn to_auth<'a>(
+        &self,
+        buf: &'a mut [u8],
+    ) -> core::result::Result<Auth<'a>, crate::CodecError> {
+        auth_encode::<_, AUTH_USRPWD_ID>(self, buf)
+    }
+
+    pub fn from_auth(auth: &Auth<'_>) -> Option<Self> {
+        auth_decode::<_, AUTH_USRPWD_ID>(auth)
+    }
+}
+
+impl UsrPwdNonce {
+    pub fn to_auth<'a>(
+        &self,
+        buf: &'a mut [u8],
+    ) -> core::result::Result<Auth<'a>, crate::CodecError> {
+        auth_encode::<_, AUTH_USRPWD_ID>(self, buf)
+    }
+
+    pub fn from_auth(auth: &Auth<'_>) -> Option<Self> {
+        auth_decode::<_, AUTH_USRPWD_ID>(auth)
+    }
+}
+
+impl<'a> UsrPwdProof<'a> {
+    pub fn to_auth<'b>(
+        &self,
+        buf: &'b mut [u8],
+    ) -> core::result::Result<Auth<'b>, crate::CodecError> {
+        auth_encode::<_, AUTH_USRPWD_ID>(self, buf)
+    }
+
+    pub fn from_auth(auth: &Auth<'a>) -> Option<Self> {
+        auth_decode::<_, AUTH_USRPWD_ID>(auth)
+    }
+}
+
+fn auth_encode<'a, 'b, T: ZExt<'a>, const ID: u8>(
+    x: &T,
+    buf: &'b mut [u8],
+) -> core::result::Result<Auth<'b>, crate::CodecError> {
+    let len = buf.len();
+
+    let mut w = &mut buf[..];
+    zext_encode::<_, ID, false>(x, &mut w, false)?;
+    let written = len - w.remaining();
+
+    let buf: &'b [u8] = buf;
+    Ok(Auth {
+        payload: &buf[..written],
+    })
+}
+
+fn auth_decode<'a, T: ZExt<'a>, const ID: u8>(auth: &Auth<'a>) -> Option<T> {
+    let mut r = auth.payload;
+
+    while r.can_read() {
+        let (id, kind, _, more) = decode_ext_header(&mut r).ok()?;
+
+        if id == ID && kind == T::KIND {
+            return zext_decode::<T>(&mut r).ok();
+        }
+
+        skip_ext(&mut r, kind).ok()?;
+
+        if !more {
+            break;
+        }
+    }
+
+    None
+}
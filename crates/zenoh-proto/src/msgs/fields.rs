@@ -1,5 +1,4 @@
-use uhlc::NTP64;
-pub use uhlc::Timestamp;
+pub use uhlc::{NTP64, Timestamp};
 
 use crate::*;
 
@@ -90,6 +89,12 @@ impl ZenohIdProto {
     pub fn as_le_bytes(&self) -> [u8; uhlc::ID::MAX_SIZE] {
         self.0.to_le_bytes()
     }
+
+    /// A timestamp at `time` issued by this ZenohId.
+    #[inline]
+    pub fn timestamp(&self, time: NTP64) -> Timestamp {
+        Timestamp::new(time, self.0)
+    }
 }
 impl core::fmt::Debug for ZenohIdProto {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
@@ -97,6 +102,12 @@ impl core::fmt::Debug for ZenohIdProto {
     }
 }
 
+impl core::fmt::Display for ZenohIdProto {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 impl Default for ZenohIdProto {
     fn default() -> Self {
         Self(uhlc::ID::rand())
@@ -282,6 +293,19 @@ pub enum Bits {
     U64 = 0b0000_0011,
 }
 
+impl Bits {
+    /// SNs and request IDs are `u32`: a 64-bit resolution is never negotiated and `U64` masks
+    /// like 32 bits.
+    pub const fn mask(&self) -> u32 {
+        match self {
+            Bits::U8 => (u8::MAX >> 1) as u32,   // 1 byte max when encoded
+            Bits::U16 => (u16::MAX >> 2) as u32, // 2 bytes max when encoded
+            Bits::U32 => u32::MAX >> 4,          // 4 bytes max when encoded
+            Bits::U64 => u32::MAX,               // never negotiated
+        }
+    }
+}
+
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Field {
@@ -294,6 +318,13 @@ pub enum Field {
 pub struct Resolution(pub(crate) u8);
 
 impl Resolution {
+    pub const fn new(frame_sn: Bits, request_id: Bits) -> Self {
+        Self(
+            ((frame_sn as u8) << (Field::FrameSN as u8))
+                | ((request_id as u8) << (Field::RequestID as u8)),
+        )
+    }
+
     pub const fn get(&self, field: Field) -> Bits {
         let value = (self.0 >> (field as u8)) & 0b11;
 
@@ -310,12 +341,15 @@ impl Resolution {
         self.0 &= !(0b11 << field as u8);
         self.0 |= (bits as u8) << (field as u8);
     }
+
+    pub const fn mask(&self, field: Field) -> u32 {
+        self.get(field).mask()
+    }
 }
+
 impl Default for Resolution {
     fn default() -> Self {
-        let frame_sn = Bits::U32 as u8;
-        let request_id = (Bits::U32 as u8) << 2;
-        Self(frame_sn | request_id)
+        Self::new(Bits::U32, Bits::U32)
     }
 }
 
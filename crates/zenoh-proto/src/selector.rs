@@ -0,0 +1,466 @@
+use core::{ops::Bound, str::FromStr, time::Duration};
+
+use uhlc::{NTP64, Timestamp};
+
+use crate::keyexpr;
+
+const PARAMETERS_SEPARATOR: char = '?';
+const LIST_SEPARATOR: char = ';';
+const FIELD_SEPARATOR: char = '=';
+
+/// The `_time` parameter holding a `TimeRange`.
+pub const TIME_RANGE_KEY: &str = "_time";
+
+/// A view over `;`-separated `key=value` query parameters.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Parameters<'a>(&'a str);
+
+impl<'a> Parameters<'a> {
+    pub const fn new(parameters: &'a str) -> Self {
+        Self(parameters)
+    }
+
+    pub const fn as_str(&self) -> &'a str {
+        self.0
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterates over the `(key, value)` pairs, a key without `=` has an empty value.
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.0
+            .split(LIST_SEPARATOR)
+            .filter(|parameter| !parameter.is_empty())
+            .map(|parameter| {
+                parameter
+                    .split_once(FIELD_SEPARATOR)
+                    .unwrap_or((parameter, ""))
+            })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&'a str> {
+        self.iter().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Percent-decodes the value of `key` into `buf`.
+    pub fn get_decoded<'b>(
+        &self,
+        key: &str,
+        buf: &'b mut [u8],
+    ) -> Option<core::result::Result<&'b str, crate::SelectorError>> {
+        self.get(key).map(|value| Self::decode(value, buf))
+    }
+
+    /// Percent-decodes `value` into `buf`.
+    pub fn decode<'b>(
+        value: &str,
+        buf: &'b mut [u8],
+    ) -> core::result::Result<&'b str, crate::SelectorError> {
+        fn hex(c: u8) -> Option<u8> {
+            match c {
+                b'0'..=b'9' => Some(c - b'0'),
+                b'a'..=b'f' => Some(c - b'a' + 10),
+                b'A'..=b'F' => Some(c - b'A' + 10),
+                _ => None,
+            }
+        }
+
+        let bytes = value.as_bytes();
+        let mut len = 0;
+
+        let mut i = 0;
+        while i < bytes.len() {
+            let byte = match bytes[i] {
+                b'%' => {
+                    let (Some(high), Some(low)) = (
+                        bytes.get(i + 1).copied().and_then(hex),
+                        bytes.get(i + 2).copied().and_then(hex),
+                    ) else {
+                        crate::zbail!(crate::SelectorError::InvalidPercentEncoding);
+                    };
+
+                    i += 3;
+                    high << 4 | low
+                }
+                byte => {
+                    i += 1;
+                    byte
+                }
+            };
+
+            let Some(dst) = buf.get_mut(len) else {
+                crate::zbail!(crate::SelectorError::DstIsTooSmall);
+            };
+
+            *dst = byte;
+            len += 1;
+        }
+
+        core::str::from_utf8(&buf[..len]).map_err(|_| crate::SelectorError::InvalidUtf8)
+    }
+
+    /// Parses the `_time` parameter, if any.
+    pub fn time_range(&self) -> Option<core::result::Result<TimeRange, crate::SelectorError>> {
+        self.get(TIME_RANGE_KEY).map(TimeRange::from_str)
+    }
+}
+
+impl core::fmt::Debug for Parameters<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl core::fmt::Display for Parameters<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl<'a> From<&'a str> for Parameters<'a> {
+    fn from(value: &'a str) -> Self {
+        Self(value)
+    }
+}
+
+/// A key expression with optional parameters, written `keyexpr?parameters`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Selector<'a> {
+    ke: &'a keyexpr,
+    parameters: Parameters<'a>,
+}
+
+impl<'a> Selector<'a> {
+    pub const fn new(ke: &'a keyexpr, parameters: &'a str) -> Self {
+        Self {
+            ke,
+            parameters: Parameters::new(parameters),
+        }
+    }
+
+    pub const fn keyexpr(&self) -> &'a keyexpr {
+        self.ke
+    }
+
+    pub const fn parameters(&self) -> Parameters<'a> {
+        self.parameters
+    }
+
+    pub fn time_range(&self) -> Option<core::result::Result<TimeRange, crate::SelectorError>> {
+        self.parameters.time_range()
+    }
+}
+
+impl core::fmt::Debug for Selector<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "sel`{self}`")
+    }
+}
+
+impl core::fmt::Display for Selector<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self.parameters.is_empty() {
+            write!(f, "{}", self.ke)
+        } else {
+            write!(f, "{}?{}", self.ke, self.parameters)
+        }
+    }
+}
+
+impl<'a> From<&'a keyexpr> for Selector<'a> {
+    fn from(value: &'a keyexpr) -> Self {
+        Self::new(value, "")
+    }
+}
+
+impl<'a> TryFrom<&'a str> for Selector<'a> {
+    type Error = crate::SelectorError;
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        let (ke, parameters) = value
+            .split_once(PARAMETERS_SEPARATOR)
+            .unwrap_or((value, ""));
+
+        Ok(Self::new(keyexpr::new(ke)?, parameters))
+    }
+}
+
+/// A bound of a `TimeRange`, either absolute or relative to the time it is resolved at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeExpr {
+    Fixed(NTP64),
+    Now { offset_secs: f64 },
+}
+
+impl TimeExpr {
+    pub fn resolve(&self, now: NTP64) -> NTP64 {
+        match self {
+            TimeExpr::Fixed(time) => *time,
+            TimeExpr::Now { offset_secs } => shift(now, *offset_secs),
+        }
+    }
+
+    fn shifted(self, secs: f64) -> Self {
+        match self {
+            TimeExpr::Fixed(time) => TimeExpr::Fixed(shift(time, secs)),
+            TimeExpr::Now { offset_secs } => TimeExpr::Now {
+                offset_secs: offset_secs + secs,
+            },
+        }
+    }
+}
+
+fn shift(time: NTP64, secs: f64) -> NTP64 {
+    let delta = NTP64::from(Duration::from_secs_f64(secs.abs().min(u32::MAX as f64))).as_u64();
+    if secs >= 0.0 {
+        NTP64(time.as_u64().saturating_add(delta))
+    } else {
+        NTP64(time.as_u64().saturating_sub(delta))
+    }
+}
+
+impl FromStr for TimeExpr {
+    type Err = crate::SelectorError;
+
+    /// Parses `now()`, `now(<duration>)`, an RFC3339 date or a number of seconds since the UNIX epoch.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(offset) = s.strip_prefix("now(").and_then(|s| s.strip_suffix(')')) {
+            let offset_secs = match offset {
+                "" => 0.0,
+                offset => parse_duration(offset)?,
+            };
+
+            return Ok(TimeExpr::Now { offset_secs });
+        }
+
+        if let Some(time) = parse_rfc3339(s) {
+            return Ok(TimeExpr::Fixed(time));
+        }
+
+        match s.parse::<f64>() {
+            Ok(secs) if (0.0..=u32::MAX as f64).contains(&secs) => {
+                Ok(TimeExpr::Fixed(NTP64::from(Duration::from_secs_f64(secs))))
+            }
+            _ => crate::zbail!(crate::SelectorError::InvalidTimeRange),
+        }
+    }
+}
+
+/// A time range such as `[now(-1h)..]`, `]2024-01-01T00:00:00Z..now()[` or `[now(-5m);1m]`.
+/// `[` and `]` on the inner side of a bound make it inclusive, on the outer side exclusive.
+///
+/// `;` also separates the parameters of a selector, so a `_time` parameter only holds the `..`
+/// forms, e.g. `[now(-5m)..now(-4m)]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeRange {
+    pub start: Bound<TimeExpr>,
+    pub end: Bound<TimeExpr>,
+}
+
+impl TimeRange {
+    /// Resolves the `now()` bounds against `now`.
+    pub fn resolve(&self, now: NTP64) -> (Bound<NTP64>, Bound<NTP64>) {
+        (
+            self.start.map(|start| start.resolve(now)),
+            self.end.map(|end| end.resolve(now)),
+        )
+    }
+
+    pub fn contains(&self, timestamp: &Timestamp, now: NTP64) -> bool {
+        let time = timestamp.get_time();
+        let (start, end) = self.resolve(now);
+
+        let after_start = match start {
+            Bound::Included(start) => *time >= start,
+            Bound::Excluded(start) => *time > start,
+            Bound::Unbounded => true,
+        };
+
+        let before_end = match end {
+            Bound::Included(end) => *time <= end,
+            Bound::Excluded(end) => *time < end,
+            Bound::Unbounded => true,
+        };
+
+        after_start && before_end
+    }
+}
+
+impl FromStr for TimeRange {
+    type Err = crate::SelectorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((inclusive_start, s)) = s
+            .strip_prefix('[')
+            .map(|s| (true, s))
+            .or_else(|| s.strip_prefix(']').map(|s| (false, s)))
+        else {
+            crate::zbail!(crate::SelectorError::InvalidTimeRange);
+        };
+
+        let Some((inclusive_end, inner)) = s
+            .strip_suffix(']')
+            .map(|s| (true, s))
+            .or_else(|| s.strip_suffix('[').map(|s| (false, s)))
+        else {
+            crate::zbail!(crate::SelectorError::InvalidTimeRange);
+        };
+
+        let bound = |expr: &str, inclusive: bool| -> Result<Bound<TimeExpr>, Self::Err> {
+            Ok(match expr {
+                "" => Bound::Unbounded,
+                expr if inclusive => Bound::Included(expr.parse()?),
+                expr => Bound::Excluded(expr.parse()?),
+            })
+        };
+
+        if let Some((start, duration)) = inner.split_once(LIST_SEPARATOR) {
+            let start: TimeExpr = match start {
+                "" => crate::zbail!(crate::SelectorError::InvalidTimeRange),
+                start => start.parse()?,
+            };
+
+            let end = start.shifted(parse_duration(duration)?);
+
+            return Ok(TimeRange {
+                start: if inclusive_start {
+                    Bound::Included(start)
+                } else {
+                    Bound::Excluded(start)
+                },
+                end: if inclusive_end {
+                    Bound::Included(end)
+                } else {
+                    Bound::Excluded(end)
+                },
+            });
+        }
+
+        let Some((start, end)) = inner.split_once("..") else {
+            crate::zbail!(crate::SelectorError::InvalidTimeRange);
+        };
+
+        Ok(TimeRange {
+            start: bound(start, inclusive_start)?,
+            end: bound(end, inclusive_end)?,
+        })
+    }
+}
+
+/// Parses a signed duration such as `-1h`, `30s`, `1.5m` or `100ms` into seconds.
+fn parse_duration(s: &str) -> core::result::Result<f64, crate::SelectorError> {
+    let unit_start = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
+    let (value, unit) = s.split_at(unit_start);
+
+    let Ok(value) = value.parse::<f64>() else {
+        crate::zbail!(crate::SelectorError::InvalidTimeRange);
+    };
+
+    if !value.is_finite() {
+        crate::zbail!(crate::SelectorError::InvalidTimeRange);
+    }
+
+    let scale = match unit {
+        "u" | "us" => 0.000_001,
+        "ms" => 0.001,
+        "" | "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3_600.0,
+        "d" => 86_400.0,
+        "w" => 604_800.0,
+        _ => crate::zbail!(crate::SelectorError::InvalidTimeRange),
+    };
+
+    Ok(value * scale)
+}
+
+/// Parses `YYYY-MM-DDTHH:MM:SS[.fraction](Z|+HH:MM|-HH:MM)`.
+fn parse_rfc3339(s: &str) -> Option<NTP64> {
+    fn number(s: &str, range: core::ops::Range<usize>) -> Option<i64> {
+        let digits = s.get(range)?;
+        if !digits.bytes().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        digits.parse().ok()
+    }
+
+    let bytes = s.as_bytes();
+    if bytes.len() < 20
+        || bytes[4] != b'-'
+        || bytes[7] != b'-'
+        || !matches!(bytes[10], b'T' | b't' | b' ')
+        || bytes[13] != b':'
+        || bytes[16] != b':'
+    {
+        return None;
+    }
+
+    let (year, month, day) = (number(s, 0..4)?, number(s, 5..7)?, number(s, 8..10)?);
+    let (hour, minute, second) = (number(s, 11..13)?, number(s, 14..16)?, number(s, 17..19)?);
+
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+
+    let mut rest = &s[19..];
+    let mut nanos = 0u32;
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let digits = fraction
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(fraction.len());
+        if digits == 0 {
+            return None;
+        }
+
+        for (i, c) in fraction[..digits.min(9)].bytes().enumerate() {
+            nanos += (c - b'0') as u32 * 10u32.pow(8 - i as u32);
+        }
+
+        rest = &fraction[digits..];
+    }
+
+    let offset = match rest {
+        "Z" | "z" => 0,
+        rest if rest.len() == 6 && rest.as_bytes()[3] == b':' => {
+            let sign = match rest.as_bytes()[0] {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return None,
+            };
+
+            sign * (number(rest, 1..3)? * 3_600 + number(rest, 4..6)? * 60)
+        }
+        _ => return None,
+    };
+
+    // Days since the UNIX epoch of a proleptic Gregorian date
+    let (y, m) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * m + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    let secs = days * 86_400 + hour * 3_600 + minute * 60 + second - offset;
+    if secs < 0 {
+        return None;
+    }
+
+    Some(NTP64::from(Duration::new(secs as u64, nanos)))
+}
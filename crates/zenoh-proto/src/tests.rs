@@ -3,4 +3,5 @@ mod ext;
 mod ke;
 mod msgs;
 mod random;
+mod selector;
 mod r#struct;
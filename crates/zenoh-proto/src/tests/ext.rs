@@ -222,3 +222,38 @@ fn test_zmsg_partial_exts() {
     };
     roundtrip!(ZMsgComplexOption, msg);
 }
+
+#[test]
+fn test_zext_usrpwd_auth() {
+    use crate::exts::*;
+
+    let mut data = [0u8; 64];
+
+    let auth = HasUsrPwd {}.to_auth(&mut data).unwrap();
+    assert_eq!(auth.payload, &[AUTH_USRPWD_ID]);
+    assert_eq!(HasUsrPwd::from_auth(&auth), Some(HasUsrPwd {}));
+    assert_eq!(UsrPwdNonce::from_auth(&auth), None);
+
+    let auth = UsrPwdNonce { nonce: 0xdead_beef }
+        .to_auth(&mut data)
+        .unwrap();
+    assert_eq!(
+        UsrPwdNonce::from_auth(&auth),
+        Some(UsrPwdNonce { nonce: 0xdead_beef })
+    );
+
+    let proof = UsrPwdProof {
+        user: b"user",
+        hmac: &[7; 32],
+    };
+    let auth = proof.to_auth(&mut data).unwrap();
+    assert_eq!(UsrPwdProof::from_auth(&auth), Some(proof));
+
+    // A pubkey (0x1) unit extension followed by the usrpwd nonce.
+    let payload = [0x81, 0x22, 0x2a];
+    let auth = Auth { payload: &payload };
+    assert_eq!(
+        UsrPwdNonce::from_auth(&auth),
+        Some(UsrPwdNonce { nonce: 0x2a })
+    );
+}
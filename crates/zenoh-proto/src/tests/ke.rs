@@ -1,4 +1,4 @@
-use crate::{KeyexprError, keyexpr};
+use crate::{KeyexprError, SetIntersectionLevel, keyexpr};
 
 fn intersect(left: &str, right: &str) -> bool {
     let left = keyexpr::new(left).unwrap();
@@ -119,3 +119,136 @@ fn keyexpr_validation() {
     assert!(ok("demo/example$*-$*/test"));
     assert!(ok("demo/example$*"));
 }
+
+fn include(left: &str, right: &str) -> bool {
+    let left = keyexpr::new(left).unwrap();
+    let right = keyexpr::new(right).unwrap();
+
+    left.includes(right)
+}
+
+fn canonize(ke: &str, expected: &str) -> bool {
+    let mut buf = [0u8; 64];
+    keyexpr::canonize(ke, &mut buf).unwrap().as_str() == expected
+}
+
+#[test]
+fn keyexpr_include() {
+    assert!(include("**", "a"));
+    assert!(include("a/**", "a"));
+    assert!(include("a/*", "a/ab"));
+    assert!(include("a/$*b", "a/ab"));
+    assert!(!include("a/$*c", "a/ab"));
+    assert!(include("*/**", "a"));
+    assert!(include("a/*/**", "a/b/c"));
+    assert!(!include("a/*/**", "a"));
+    assert!(include("a/**/b", "a/b"));
+    assert!(!include("a/*", "a/**"));
+    assert!(include("a/**", "a/*/**"));
+    assert!(include("ab$*", "abcd"));
+    assert!(include("ab$*d", "abcd"));
+    assert!(include("ab$*", "ab"));
+    assert!(!include("ab/*", "ab"));
+    assert!(include("a/*/c/*/e", "a/b/c/d/e"));
+    assert!(include("a/$*b/c/$*d/e", "a/xb/c/xd/e"));
+    assert!(!include("a/*/c/*/e", "a/c/e"));
+    assert!(!include("a/*/c/*/e", "a/b/c/d/x/e"));
+    assert!(!include("ab$*cd", "abxxcxxd"));
+    assert!(include("ab$*cd", "abxxcxxcd"));
+    assert!(!include("ab$*cd", "abxxcxxcdx"));
+    assert!(include("**", "abc"));
+    assert!(include("**", "a/b/c"));
+    assert!(include("ab/**", "ab"));
+    assert!(include("**/xyz", "a/b/xyz/d/e/f/xyz"));
+    assert!(!include("**/xyz$*xyz", "a/b/xyz/d/e/f/xyz"));
+    assert!(include("**/xyz$*xyz", "a/b/xyzdefxyz"));
+    assert!(include("a/**/c/**/e", "a/b/b/b/c/d/d/d/e"));
+    assert!(include("a/**/c/**/e", "a/c/e"));
+    assert!(include("a/**/c/*/e/*", "a/b/b/b/c/d/d/c/d/e/f"));
+    assert!(!include("a/**/c/*/e/*", "a/b/b/b/c/d/d/c/d/d/e/f"));
+    assert!(include("x/abc", "x/abc"));
+    assert!(!include("x/abc", "abc"));
+    assert!(include("x/*", "x/abc"));
+    assert!(!include("x/*", "abc"));
+    assert!(!include("*", "x/abc"));
+    assert!(include("x/*", "x/abc$*"));
+    assert!(!include("x/$*abc", "x/abc$*"));
+    assert!(include("x/a$*", "x/abc$*"));
+    assert!(include("x/a$*de", "x/abc$*de"));
+    assert!(!include("x/a$*d$*e", "x/a$*e"));
+    assert!(include("x/a$*d$*e", "x/a$*d$*e"));
+    assert!(include("x/a$*d$*e", "x/ade"));
+    assert!(!include("x/c$*", "x/abc$*"));
+    assert!(!include("x/$*d", "x/$*e"));
+
+    assert!(include("@a", "@a"));
+    assert!(!include("@a", "@ab"));
+    assert!(!include("@a", "@a/b"));
+    assert!(!include("@a", "@a/*"));
+    assert!(!include("@a", "@a/**"));
+    assert!(include("@a/**", "@a"));
+    assert!(!include("**", "@a"));
+    assert!(!include("*", "@a"));
+    assert!(!include("@a/*", "@a/@b"));
+    assert!(!include("@a/**", "@a/@b"));
+    assert!(include("@a/**/@b", "@a/@b"));
+    assert!(include("@a/@b/**", "@a/@b"));
+    assert!(include("@a/**/e", "@a/b/b/d/d/d/e"));
+    assert!(!include("@a/**/e", "@a/b/b/@c/b/d/d/d/e"));
+}
+
+#[test]
+fn keyexpr_relation() {
+    let relation = |left: &str, right: &str| {
+        keyexpr::new(left)
+            .unwrap()
+            .relation_to(keyexpr::new(right).unwrap())
+    };
+
+    assert_eq!(relation("a/b", "a/b"), SetIntersectionLevel::Equals);
+    assert_eq!(relation("a/**", "a/b"), SetIntersectionLevel::Includes);
+    assert_eq!(relation("a/b", "a/**"), SetIntersectionLevel::Intersects);
+    assert_eq!(relation("a/*/c", "a/b/*"), SetIntersectionLevel::Intersects);
+    assert_eq!(relation("a/b", "a/c"), SetIntersectionLevel::Disjoint);
+}
+
+#[test]
+fn keyexpr_canonize() {
+    assert!(canonize("hello/$*/bye", "hello/*/bye"));
+    assert!(canonize("hello/$*$*/bye", "hello/*/bye"));
+    assert!(canonize("$*", "*"));
+    assert!(canonize("$*hi/$*$*/$*$*$*", "$*hi/*/*"));
+    assert!(canonize("hi$*$*there", "hi$*there"));
+    assert!(canonize("hello/**/**/bye", "hello/**/bye"));
+    assert!(canonize("hello/**/**", "hello/**"));
+    assert!(canonize("hello/**/*/bye", "hello/*/**/bye"));
+    assert!(canonize("hello/**/*", "hello/*/**"));
+    assert!(canonize("hello/**/$*/**/*/bye", "hello/*/*/**/bye"));
+    assert!(canonize("**/*/**", "*/**"));
+    assert!(canonize("a/b/c", "a/b/c"));
+
+    let mut small = [0u8; 4];
+    assert!(matches!(
+        keyexpr::canonize("hello/**", &mut small),
+        Err(KeyexprError::DstIsTooSmall)
+    ));
+    assert!(matches!(
+        keyexpr::canonize("hello//bye", &mut [0u8; 64]),
+        Err(KeyexprError::EmptyChunk)
+    ));
+}
+
+#[test]
+fn keyexpr_chunks() {
+    let ke = keyexpr::new("a/*/b$*/**").unwrap();
+    let mut chunks = ke.chunks();
+
+    assert_eq!(chunks.next().map(keyexpr::as_str), Some("a"));
+    assert_eq!(chunks.next_back().map(keyexpr::as_str), Some("**"));
+    assert_eq!(chunks.next().map(keyexpr::as_str), Some("*"));
+    assert_eq!(chunks.next().map(keyexpr::as_str), Some("b$*"));
+    assert!(chunks.next().is_none());
+
+    assert!(ke.is_wild());
+    assert!(!keyexpr::new("a/b").unwrap().is_wild());
+}
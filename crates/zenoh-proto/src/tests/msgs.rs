@@ -105,7 +105,7 @@ const NUM_ITER: usize = 100;
 const MAX_PAYLOAD_SIZE: usize = 512;
 
 roundtrips!(ext, zenoh, EntityGlobalId, SourceInfo, Value, Attachment);
-roundtrips!(zenoh, Err, Put, Query, Reply,);
+roundtrips!(zenoh, Del, Err, Put, Query, Reply,);
 
 roundtrips!(
     ext,
@@ -289,3 +289,103 @@ fn transport_stream() {
     assert!(messages.is_empty());
     assert!(got_keepalive);
 }
+
+#[test]
+fn frame_sn_wraparound() {
+    extern crate std;
+
+    let mut data = [0u8; MAX_PAYLOAD_SIZE];
+
+    for bits in [Bits::U8, Bits::U16, Bits::U32, Bits::U64] {
+        let mut batch = BatchWriter::with_resolution(&mut data[..], bits.mask(), bits);
+
+        for r in [
+            Reliability::Reliable,
+            Reliability::BestEffort,
+            Reliability::Reliable,
+        ] {
+            batch
+                .framed(&ResponseFinal::default(), r, QoS::default())
+                .unwrap();
+        }
+
+        let (next_sn, len) = batch.finalize();
+        assert_eq!(next_sn, 2);
+
+        let sns = BatchReader::new(&data[..len])
+            .map(|msg| match msg {
+                Message::ResponseFinal { frame, .. } => frame.sn,
+                _ => panic!("Expected a ResponseFinal"),
+            })
+            .collect::<std::vec::Vec<_>>();
+
+        assert_eq!(sns, [bits.mask(), 0, 1]);
+    }
+}
+
+#[test]
+fn frame_sn_out_of_resolution() {
+    let mut data = [0u8; MAX_PAYLOAD_SIZE];
+
+    let mut batch = BatchWriter::with_resolution(&mut data[..], Bits::U8.mask() + 1, Bits::U16);
+    batch
+        .framed(
+            &ResponseFinal::default(),
+            Reliability::Reliable,
+            QoS::default(),
+        )
+        .unwrap();
+    let (_, len) = batch.finalize();
+
+    assert!(
+        BatchReader::new(&data[..len])
+            .resolution(Bits::U16)
+            .next()
+            .is_some()
+    );
+    assert!(
+        BatchReader::new(&data[..len])
+            .resolution(Bits::U8)
+            .next()
+            .is_none()
+    );
+}
+
+#[test]
+fn lowlatency_stream() {
+    extern crate std;
+    use std::collections::VecDeque;
+
+    let mut rand = [0u8; MAX_PAYLOAD_SIZE * NUM_ITER];
+    let mut rw = rand.as_mut_slice();
+
+    let mut messages = {
+        let mut msgs = VecDeque::new();
+        for _ in 1..thread_rng().gen_range(2..16) {
+            msgs.push_back(FrameBody::rand(&mut rw));
+        }
+        msgs
+    };
+
+    let mut data = [0u8; MAX_PAYLOAD_SIZE * NUM_ITER];
+    let mut batch = BatchWriter::new(&mut data[..], 0).lowlatency(true);
+
+    for msg in &messages {
+        batch
+            .framed(msg, Reliability::default(), QoS::default())
+            .unwrap();
+    }
+
+    let (sn, len) = batch.finalize();
+    assert_eq!(sn, 0);
+    assert_ne!(data[0] & 0b0001_1111, FrameHeader::ID);
+
+    assert_eq!(BatchReader::new(&data[..len]).count(), 0);
+
+    for msg in BatchReader::new(&data[..len]).lowlatency(true) {
+        let actual = messages.pop_front().unwrap();
+        assert!(actual.is(&msg));
+    }
+
+    assert!(messages.is_empty());
+}
@@ -0,0 +1,177 @@
+use core::{ops::Bound, str::FromStr, time::Duration};
+
+use uhlc::{ID, NTP64, Timestamp};
+
+use crate::{Parameters, Selector, SelectorError, TimeExpr, TimeRange};
+
+fn secs(secs: u64) -> NTP64 {
+    NTP64::from(Duration::from_secs(secs))
+}
+
+fn timestamp(secs: u64) -> Timestamp {
+    Timestamp::new(self::secs(secs), ID::try_from([1]).unwrap())
+}
+
+#[test]
+fn parameters_iter() {
+    let parameters = Parameters::new("a=1;b;c=x=y;;d=");
+    let mut iter = parameters.iter();
+
+    assert_eq!(iter.next(), Some(("a", "1")));
+    assert_eq!(iter.next(), Some(("b", "")));
+    assert_eq!(iter.next(), Some(("c", "x=y")));
+    assert_eq!(iter.next(), Some(("d", "")));
+    assert_eq!(iter.next(), None);
+
+    assert_eq!(parameters.get("a"), Some("1"));
+    assert_eq!(parameters.get("b"), Some(""));
+    assert_eq!(parameters.get("e"), None);
+    assert!(parameters.contains_key("d"));
+    assert!(!parameters.contains_key("x"));
+    assert!(Parameters::new("").iter().next().is_none());
+}
+
+#[test]
+fn parameters_decode() {
+    let mut buf = [0u8; 16];
+    let parameters = Parameters::new("name=hello%20world%3B;bad=%2;utf8=%C3%A9;invalid=%FF");
+
+    assert_eq!(
+        parameters.get_decoded("name", &mut buf).unwrap().unwrap(),
+        "hello world;"
+    );
+    assert_eq!(
+        parameters.get_decoded("utf8", &mut buf).unwrap().unwrap(),
+        "é"
+    );
+    assert!(matches!(
+        parameters.get_decoded("bad", &mut buf),
+        Some(Err(SelectorError::InvalidPercentEncoding))
+    ));
+    assert!(matches!(
+        parameters.get_decoded("invalid", &mut buf),
+        Some(Err(SelectorError::InvalidUtf8))
+    ));
+    assert!(matches!(
+        Parameters::decode("hello world", &mut [0u8; 4]),
+        Err(SelectorError::DstIsTooSmall)
+    ));
+    assert!(parameters.get_decoded("missing", &mut buf).is_none());
+}
+
+#[test]
+fn selector_parse() {
+    let selector = Selector::try_from("sensors/**?_time=[now(-1h)..];id=3").unwrap();
+    assert_eq!(selector.keyexpr().as_str(), "sensors/**");
+    assert_eq!(selector.parameters().get("id"), Some("3"));
+
+    let range = selector.time_range().unwrap().unwrap();
+    assert_eq!(
+        range.start,
+        Bound::Included(TimeExpr::Now {
+            offset_secs: -3600.0
+        })
+    );
+    assert_eq!(range.end, Bound::Unbounded);
+
+    // The duration form is split as two parameters
+    let selector = Selector::try_from("sensors/**?_time=[now(-5m);1m]").unwrap();
+    assert_eq!(
+        selector.time_range(),
+        Some(Err(SelectorError::InvalidTimeRange))
+    );
+
+    let selector = Selector::try_from("a/b").unwrap();
+    assert!(selector.parameters().is_empty());
+    assert!(selector.time_range().is_none());
+
+    assert!(matches!(
+        Selector::try_from("a//b?x=1"),
+        Err(SelectorError::EmptyChunk)
+    ));
+}
+
+#[test]
+fn time_range_parse() {
+    let range = |s: &str| TimeRange::from_str(s).unwrap();
+
+    assert_eq!(
+        range("[..]"),
+        TimeRange {
+            start: Bound::Unbounded,
+            end: Bound::Unbounded
+        }
+    );
+    assert_eq!(
+        range("]now(-5m)..now()[").start,
+        Bound::Excluded(TimeExpr::Now {
+            offset_secs: -300.0
+        })
+    );
+    assert_eq!(
+        range("]now(-5m)..now()[").end,
+        Bound::Excluded(TimeExpr::Now { offset_secs: 0.0 })
+    );
+    assert_eq!(
+        range("[now(-1.5h);30m]").end,
+        Bound::Included(TimeExpr::Now {
+            offset_secs: -3600.0
+        })
+    );
+    assert_eq!(
+        range("[1700000000..1700000060.5]").start,
+        Bound::Included(TimeExpr::Fixed(secs(1_700_000_000)))
+    );
+    assert_eq!(
+        range("[1970-01-02T00:00:00Z..2024-02-29T12:30:15.25+01:00]"),
+        TimeRange {
+            start: Bound::Included(TimeExpr::Fixed(secs(86_400))),
+            end: Bound::Included(TimeExpr::Fixed(
+                secs(1_709_206_215) + NTP64::from(Duration::from_millis(250))
+            )),
+        }
+    );
+
+    for invalid in [
+        "",
+        "[",
+        "]",
+        "[]",
+        "now()..",
+        "[now()]",
+        "[;1h]",
+        "[now(-1y)..]",
+        "[yesterday..]",
+        "[-1..]",
+        "[2024-13-01T00:00:00Z..]",
+        "[now(inf)..]",
+    ] {
+        assert!(
+            matches!(
+                TimeRange::from_str(invalid),
+                Err(SelectorError::InvalidTimeRange)
+            ),
+            "{invalid}"
+        );
+    }
+}
+
+#[test]
+fn time_range_contains() {
+    let now = secs(10_000);
+
+    let range = TimeRange::from_str("[now(-1h)..]").unwrap();
+    assert!(range.contains(&timestamp(6_400), now));
+    assert!(range.contains(&timestamp(20_000), now));
+    assert!(!range.contains(&timestamp(6_399), now));
+
+    let range = TimeRange::from_str("]100..200[").unwrap();
+    assert!(!range.contains(&timestamp(100), now));
+    assert!(range.contains(&timestamp(150), now));
+    assert!(!range.contains(&timestamp(200), now));
+
+    let range = TimeRange::from_str("[100;100]").unwrap();
+    assert!(range.contains(&timestamp(100), now));
+    assert!(range.contains(&timestamp(200), now));
+    assert!(!range.contains(&timestamp(201), now));
+}
@@ -39,7 +39,7 @@ crate::declare_zerror! {
     }
 
     #[doc = "Errors related to zenoh key expression parsing."]
-    pub enum KeyexprError {
+    pub enum KeyexprError: BytesError {
         #[doc = "A lone `$*` was found in an expression."]
         #[err = "lone '$*' in expression"]
         LoneDollarStar = 20,
@@ -67,6 +67,22 @@ crate::declare_zerror! {
         #[doc = "A wildcard chunk was found where it is not allowed."]
         #[err = "wildcard chunk not allowed"]
         WildChunk = 28,
+        #[doc = "A value bound to a chunk contains a `/`."]
+        #[err = "'/' in chunk value"]
+        SlashInChunk = 29,
+    }
+
+    #[doc = "Errors related to zenoh selectors and their parameters."]
+    pub enum SelectorError: KeyexprError {
+        #[doc = "An invalid percent-encoded sequence was found in a parameter."]
+        #[err = "invalid percent-encoding in parameter"]
+        InvalidPercentEncoding = 90,
+        #[doc = "A decoded parameter is not valid UTF-8."]
+        #[err = "decoded parameter is not valid utf-8"]
+        InvalidUtf8 = 91,
+        #[doc = "Could not parse a time range."]
+        #[err = "could not parse time range"]
+        InvalidTimeRange = 92,
     }
 
     // --- IO related errors (not used in this crate) ---
@@ -121,6 +137,9 @@ crate::declare_zerror! {
         #[doc = "Transport lease timed out."]
         #[err = "transport lease timed out"]
         LeaseTimeout = 42,
+        #[doc = "Transport authentication failed."]
+        #[err = "transport authentication failed"]
+        AuthFailed = 43,
         #[doc = "Transport has been closed."]
         #[err = "transport has been closed"]
         TransportClosed = 53,
@@ -160,6 +179,21 @@ crate::declare_zerror! {
         #[doc = "Request timed out."]
         #[err = "request timed out"]
         RequestTimedout = 81,
+        #[doc = "Reply key expression does not intersect the query."]
+        #[err = "reply key expression does not intersect the query"]
+        KeyexprMismatch = 82,
+        #[doc = "Invalid session configuration."]
+        #[err = "invalid session configuration"]
+        InvalidConfig = 83,
+        #[doc = "Could not draw random bytes from the platform."]
+        #[err = "could not draw random bytes"]
+        CouldNotFillRandom = 84,
+    }
+}
+
+impl From<core::convert::Infallible> for Error {
+    fn from(value: core::convert::Infallible) -> Self {
+        match value {}
     }
 }
 
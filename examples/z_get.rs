@@ -45,13 +45,17 @@ async fn entry(spawner: embassy_executor::Spawner) -> zenoh::ZResult<()> {
     // See the `z_sub` example to see how the `channel` API works.
 
     session
-        .get(zenoh::keyexpr::new("demo/example/**")?)
+        .get(zenoh::keyexpr::new("demo/example/**")?)?
         .callback(async |resp| response_callback(resp).await)
+        .on_complete(async |reason| match reason {
+            zenoh::CompletionReason::Final => zenoh::info!("[Get] Query completed"),
+            zenoh::CompletionReason::Timeout => zenoh::info!("[Get] Query timed out"),
+        })
         .finish()
         .await?;
 
     session
-        .get(zenoh::keyexpr::new("demo/example/**")?)
+        .get("demo/example/**?_time=[now(-1h)..]")?
         .callback_sync(response_callback_sync)
         .finish()
         .await?;
@@ -0,0 +1,91 @@
+#![cfg_attr(feature = "esp32s3", no_std)]
+#![cfg_attr(feature = "esp32s3", no_main)]
+#![cfg_attr(feature = "wasm", no_main)]
+
+use zenoh_examples::*;
+use zenoh_nostd as zenoh;
+
+zenoh::kedefine!(liveliness_token: "demo/example/$name");
+
+async fn entry(spawner: embassy_executor::Spawner) -> zenoh::ZResult<()> {
+    #[cfg(feature = "log")]
+    env_logger::init();
+
+    zenoh::info!("zenoh-nostd z_liveliness example");
+
+    let config = init_example(&spawner).await;
+    let mut resources = zenoh::Resources::new();
+
+    let session = zenoh::open(&mut resources, config, zenoh::EndPoint::try_from(CONNECT)?).await?;
+
+    let _subscriber = session
+        .liveliness()
+        .declare_subscriber(zenoh::keyexpr::new("demo/example/**")?)
+        .callback_sync(|sample| match sample.kind() {
+            zenoh::SampleKind::Put => {
+                let name = liveliness_token
+                    .parse(sample.keyexpr())
+                    .and_then(|parsed| parsed.get("name"));
+
+                zenoh::info!(
+                    "[Liveliness] New alive token ('{}', name: {:?})",
+                    sample.keyexpr().as_str(),
+                    name
+                )
+            }
+            zenoh::SampleKind::Delete => {
+                zenoh::info!(
+                    "[Liveliness] Dropped token ('{}')",
+                    sample.keyexpr().as_str()
+                )
+            }
+        })
+        .finish()
+        .await?;
+
+    let token_ke: zenoh::OwnedKeyExpr<64> =
+        zenoh::keformat!(liveliness_token, name = "zenoh-nostd-liveliness")?;
+
+    let _token = session
+        .liveliness()
+        .declare_token(&token_ke)
+        .finish()
+        .await?;
+
+    session.run().await
+}
+
+#[cfg_attr(feature = "std", embassy_executor::main)]
+#[cfg_attr(feature = "wasm", embassy_executor::main)]
+#[cfg_attr(feature = "esp32s3", esp_rtos::main)]
+async fn main(spawner: embassy_executor::Spawner) {
+    if let Err(e) = entry(spawner).await {
+        zenoh::error!("Error in main: {}", e);
+    }
+
+    zenoh::info!("Exiting main");
+}
+
+#[cfg(feature = "esp32s3")]
+mod esp32s3_app {
+    use esp_hal::rng::Rng;
+    pub use esp_println as _;
+    use getrandom::{Error, register_custom_getrandom};
+
+    #[panic_handler]
+    fn panic(info: &core::panic::PanicInfo) -> ! {
+        zenoh_nostd::error!("Panic: {}", info);
+
+        loop {}
+    }
+
+    extern crate alloc;
+
+    esp_bootloader_esp_idf::esp_app_desc!();
+
+    register_custom_getrandom!(getrandom_custom);
+    pub fn getrandom_custom(bytes: &mut [u8]) -> Result<(), Error> {
+        Rng::new().read(bytes);
+        Ok(())
+    }
+}
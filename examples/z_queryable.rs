@@ -23,7 +23,7 @@ async fn entry(spawner: embassy_executor::Spawner) -> zenoh::ZResult<()> {
     static CHANNEL: static_cell::StaticCell<
         embassy_sync::channel::Channel<
             embassy_sync::blocking_mutex::raw::NoopRawMutex,
-            zenoh::OwnedQuery<ExampleConfig, 128, 128, 128>,
+            zenoh::OwnedQuery<128, 128, 128>,
             8,
         >,
     > = static_cell::StaticCell::new();
@@ -37,9 +37,6 @@ async fn entry(spawner: embassy_executor::Spawner) -> zenoh::ZResult<()> {
         zenoh::SessionError::CouldNotSpawnEmbassyTask
     })?;
 
-    // Because of known limitations, the `Queryable` API (both callbacks and channels) needs the `session` to be static.
-    // This may change in the future.
-
     let queryable = session
         .declare_queryable(zenoh::keyexpr::new("demo/example/**")?)
         .channel(channel.dyn_sender(), channel.dyn_receiver())
@@ -67,6 +64,7 @@ async fn entry(spawner: embassy_executor::Spawner) -> zenoh::ZResult<()> {
 
         let _ = query
             .reply(query.keyexpr(), b"Response from z_queryable")
+            .finish()
             .await;
 
         let _ = query.finalize().await;
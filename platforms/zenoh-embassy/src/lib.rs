@@ -24,6 +24,13 @@ impl ZPlatform for PlatformEmbassy {
     type UdpSocket = udp::EmbassyUdpSocket;
     type WebSocket = zenoh_nostd::platform::ws::DummyWsStream;
 
+    fn fill_random(&self, buf: &mut [u8]) -> core::result::Result<(), zenoh_nostd::SessionError> {
+        getrandom::getrandom(buf).map_err(|_| {
+            zenoh_nostd::error!("Could not draw random bytes");
+            zenoh_nostd::SessionError::CouldNotFillRandom
+        })
+    }
+
     async fn new_tcp_stream(
         &self,
         addr: &core::net::SocketAddr,
@@ -15,6 +15,23 @@ impl ZPlatform for PlatformStd {
     type UdpSocket = udp::StdUdpSocket;
     type WebSocket = ws::StdWsStream;
 
+    fn fill_random(&self, buf: &mut [u8]) -> core::result::Result<(), zenoh_nostd::SessionError> {
+        getrandom::getrandom(buf).map_err(|_| {
+            zenoh_nostd::error!("Could not draw random bytes");
+            zenoh_nostd::SessionError::CouldNotFillRandom
+        })
+    }
+
+    fn unix_time_offset(&self) -> Option<core::time::Duration> {
+        let unix = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()?;
+
+        unix.checked_sub(core::time::Duration::from_micros(
+            embassy_time::Instant::now().as_micros(),
+        ))
+    }
+
     async fn new_tcp_stream(
         &self,
         addr: &core::net::SocketAddr,
@@ -10,6 +10,13 @@ impl ZPlatform for PlatformWasm {
     type UdpSocket = zenoh_nostd::platform::udp::DummyUdpSocket;
     type WebSocket = ws::WasmWebSocket;
 
+    fn fill_random(&self, buf: &mut [u8]) -> core::result::Result<(), zenoh_nostd::SessionError> {
+        getrandom::getrandom(buf).map_err(|_| {
+            zenoh_nostd::error!("Could not draw random bytes");
+            zenoh_nostd::SessionError::CouldNotFillRandom
+        })
+    }
+
     async fn new_websocket_stream(
         &self,
         addr: &std::net::SocketAddr,
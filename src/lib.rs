@@ -1,8 +1,9 @@
 #![no_std]
 
 use zenoh_nostd::{
-    FixedCapacityGetCallbacks, FixedCapacityQueryableCallbacks, FixedCapacitySubCallbacks, ZConfig,
-    storage::RawOrBox,
+    FixedCapacityCompletionCallbacks, FixedCapacityConsolidation, FixedCapacityGetCallbacks,
+    FixedCapacityInterests, FixedCapacityKeyExprs, FixedCapacityMatchingCallbacks,
+    FixedCapacityQueryableCallbacks, FixedCapacitySubCallbacks, ZConfig, storage::RawOrBox,
 };
 
 #[cfg(feature = "std")]
@@ -77,6 +78,14 @@ impl ZConfig for ExampleConfig {
     type QueryableCallbacks<'res> =
         FixedCapacityQueryableCallbacks<'res, Self, 8, RawOrBox<32>, RawOrBox<952>>;
 
+    type MatchingCallbacks<'res> = FixedCapacityMatchingCallbacks<'res, 8>;
+
+    type CompletionCallbacks<'res> = FixedCapacityCompletionCallbacks<'res, 8>;
+
+    type KeyExprs = FixedCapacityKeyExprs<8>;
+    type Interests = FixedCapacityInterests<16>;
+    type Consolidation = FixedCapacityConsolidation<8>;
+
     type TxBuf = [u8; BUFF_SIZE as usize];
     type RxBuf = [u8; BUFF_SIZE as usize];
 
@@ -91,6 +100,16 @@ impl ZConfig for ExampleConfig {
     fn into_parts(self) -> (Self::Platform, Self::TxBuf, Self::RxBuf) {
         (self.platform, self.tx, self.rx)
     }
+
+    #[cfg(feature = "compression")]
+    fn compression_bufs(&mut self) -> Option<(&'static mut [u8], &'static mut [u8])> {
+        static TX: static_cell::ConstStaticCell<[u8; BUFF_SIZE as usize]> =
+            static_cell::ConstStaticCell::new([0; BUFF_SIZE as usize]);
+        static RX: static_cell::ConstStaticCell<[u8; BUFF_SIZE as usize]> =
+            static_cell::ConstStaticCell::new([0; BUFF_SIZE as usize]);
+
+        Some((TX.take(), RX.take()))
+    }
 }
 
 pub async fn init_example(spawner: &embassy_executor::Spawner) -> ExampleConfig {